@@ -1,5 +1,8 @@
-use database::{schema, PostgresPool, Status};
-use diesel::{dsl::sql, sql_types::SmallInt, ExpressionMethods, QueryDsl, RunQueryDsl};
+use database::{models::NewNameEvent, schema, PostgresPool, Source, Status};
+use diesel::{
+	dsl::sql, sql_types::SmallInt, Connection, ExpressionMethods, PgConnection, QueryDsl,
+	RunQueryDsl,
+};
 
 use crate::connectors::prelude::Connector;
 
@@ -13,6 +16,45 @@ impl Postgres {
 	}
 }
 
+/// Locks the rows for `names` and returns their current statuses.
+fn previous_statuses(
+	connection: &mut PgConnection,
+	names: &[String],
+) -> diesel::QueryResult<Vec<(String, i16)>> {
+	schema::name::table
+		.select((schema::name::username, schema::name::status))
+		.filter(schema::name::username.eq_any(names))
+		.for_update()
+		.load(connection)
+}
+
+/// Records a `name_event` for every name whose status really changed.
+fn record_transitions(
+	connection: &mut PgConnection,
+	previous: &[(String, i16)],
+	next: impl Fn(Status) -> Status,
+) -> diesel::QueryResult<usize> {
+	let events = previous
+		.iter()
+		.filter_map(|(username, status)| {
+			let status = Status::from(*status);
+			let next = next(status);
+
+			status
+				.is_transition(next)
+				.then(|| NewNameEvent::new(username, status, next, Source::Batcher))
+		})
+		.collect::<Vec<_>>();
+
+	if events.is_empty() {
+		return Ok(0);
+	}
+
+	diesel::insert_into(schema::name_event::table)
+		.values(events)
+		.execute(connection)
+}
+
 impl Connector for Postgres {
 	fn next(&mut self, size: i64) -> Option<Vec<String>> {
 		schema::name::table
@@ -41,26 +83,42 @@ impl Connector for Postgres {
 		// "if the status is Status::Available or Status::Banned, then don't change it, otherwise set it to Status::BatchAvailable"
 		let case = sql::<SmallInt>("CASE WHEN \"status\" IN (1, 3) THEN \"status\" ELSE 4 END");
 
-		diesel::update(schema::name::table)
-			.filter(schema::name::username.eq_any(&names))
-			.set((
-				schema::name::checked_at.eq(diesel::dsl::now),
-				schema::name::status.eq(case),
-			))
-			.execute(&mut database::get_pool().get()?)?;
+		self.pool.get()?.transaction(|connection| {
+			let previous = previous_statuses(connection, &names)?;
+
+			diesel::update(schema::name::table)
+				.filter(schema::name::username.eq_any(&names))
+				.set((
+					schema::name::checked_at.eq(diesel::dsl::now),
+					schema::name::status.eq(case),
+				))
+				.execute(connection)?;
+
+			// mirrors the `CASE` expression above
+			record_transitions(connection, &previous, |status| match status {
+				Status::Available | Status::Banned => status,
+				_ => Status::BatchAvailable,
+			})
+		})?;
 
 		Ok(())
 	}
 
 	fn submit_unavailable(&self, names: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
-		// set unavailable names to Status::Taken
-		diesel::update(schema::name::table)
-			.filter(schema::name::username.eq_any(&names))
-			.set((
-				schema::name::checked_at.eq(diesel::dsl::now),
-				schema::name::status.eq(i16::from(Status::BatchTaken)),
-			))
-			.execute(&mut database::get_pool().get()?)?;
+		self.pool.get()?.transaction(|connection| {
+			let previous = previous_statuses(connection, &names)?;
+
+			// set unavailable names to Status::Taken
+			diesel::update(schema::name::table)
+				.filter(schema::name::username.eq_any(&names))
+				.set((
+					schema::name::checked_at.eq(diesel::dsl::now),
+					schema::name::status.eq(i16::from(Status::BatchTaken)),
+				))
+				.execute(connection)?;
+
+			record_transitions(connection, &previous, |_| Status::BatchTaken)
+		})?;
 
 		Ok(())
 	}
@@ -10,6 +10,9 @@ mod connectors;
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	dotenvy::dotenv().ok();
 
+	metrics::batcher::register();
+	metrics::spawn_from_env("BATCHER_METRICS_ADDR", "0.0.0.0:9101");
+
 	let pool = database::get_pool();
 	let mut connector = connectors::sources::postgres::Postgres::new(pool);
 
@@ -18,6 +21,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	let mut start = std::time::Instant::now();
 
 	while let Some(mut batch) = connector.next(100) {
+		metrics::batcher::BATCH_SIZE.observe(batch.len() as f64);
+
 		let mut result = futures::stream::iter(batch.iter().map(|chunk| {
 			let client = client.clone();
 
@@ -83,6 +88,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 			retry.len()
 		);
 
+		for (result, count) in [
+			("available", available.len()),
+			("unavailable", unavailable.len()),
+			("skipped", retry.len()),
+		] {
+			metrics::batcher::NAMES
+				.with_label_values(&[result])
+				.inc_by(count as u64);
+		}
+
 		if !available.is_empty() {
 			connector.submit_available(available)?;
 		}
@@ -95,6 +110,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 		// otherwise, pause for 4 seconds since the last check
 		if pause {
 			println!("pausing for 5 minutes");
+			metrics::batcher::PAUSES.inc();
 			tokio::time::sleep(std::time::Duration::from_secs(300)).await;
 		} else {
 			// get the number of milliseconds elapsed since the last check
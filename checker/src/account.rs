@@ -65,6 +65,7 @@ impl<'a> Account<'a> {
 		}
 
 		self.clients.remove(self.index);
+		metrics::checker::PROXY_REMOVALS.inc();
 	}
 
 	pub fn get_client(&mut self) -> Option<&Client> {
@@ -1,12 +1,22 @@
 use api::microsoft::JavaData;
-use database::{functions::date_trunc, models::Snipe, schema, PostgresPool, Status};
+use database::{
+	alerts, channels,
+	crypto::Secret,
+	models::{self, NewNameEvent, Snipe},
+	proxies, schema, tiers,
+	webhooks::{self, Event},
+	PostgresPool, Source, Status,
+};
 use diesel::{
-	dsl::sql, sql_types::Timestamptz, BoolExpressionMethods, ExpressionMethods, IntoSql, QueryDsl,
-	Queryable, RunQueryDsl,
+	BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, PgConnection,
+	QueryDsl, QueryResult, RunQueryDsl, SelectableHelper,
 };
 use once_cell::sync::Lazy;
 use reqwest::Client;
-use tokio::sync::Mutex;
+use tokio::{
+	sync::Mutex,
+	time::{Duration, Instant},
+};
 
 use crate::{
 	account::{Account, CACHE_DIR},
@@ -19,26 +29,18 @@ pub struct Postgres {
 	medium: Vec<String>,
 	low: Vec<String>,
 	snipe: Option<Snipe>,
+	/// The slot this worker holds on `snipe`, which is also its index among the workers
+	snipe_slot: i16,
 	snipe_token: Option<JavaData>,
+	snipe_refreshed_at: Instant,
 	pool: PostgresPool,
 	client: Option<Client>,
 }
 
-#[derive(Queryable)]
-pub struct AccountData {
-	username: String,
-	password: String,
-}
-
-#[derive(Queryable)]
-pub struct ProxyData {
-	address: String,
-	port: i32,
-	username: Option<String>,
-	password: Option<String>,
-}
-
 static SNIPE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+pub(crate) static SECRET: Lazy<Secret> = Lazy::new(Secret::from_env);
+/// How often a worker checks whether its snipe has been cancelled or resized
+const SNIPE_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
 
 impl Postgres {
 	pub fn new(pool: PostgresPool, client: Option<Client>) -> Self {
@@ -48,10 +50,75 @@ impl Postgres {
 			low: Vec::new(),
 			pool,
 			snipe: None,
+			snipe_slot: 0,
 			snipe_token: None,
+			snipe_refreshed_at: Instant::now(),
 			client,
 		}
 	}
+
+	/// Re-reads the current snipe so that cancelled snipes are dropped and resized
+	/// snipes release the slots of workers that are no longer needed.
+	fn refresh_snipe(&mut self) {
+		let Some(snipe) = self.snipe.as_mut() else {
+			return;
+		};
+
+		let Ok(mut connection) = self.pool.get() else {
+			return;
+		};
+
+		self.snipe_refreshed_at = Instant::now();
+
+		let needed = schema::snipe::table
+			.select(schema::snipe::needed)
+			.filter(schema::snipe::username.eq(&snipe.username))
+			.get_result::<i16>(&mut connection)
+			.optional();
+
+		match needed {
+			Ok(Some(needed)) if self.snipe_slot < needed => {
+				snipe.needed = needed;
+
+				return;
+			}
+			// a cancelled or sniped snipe takes its slots with it
+			Ok(None) => {}
+			Ok(Some(_)) => {
+				// keep the snipe and try again on the next refresh if the slot could not be given back
+				if release_slot(&mut connection, &snipe.username, self.snipe_slot).is_err() {
+					return;
+				}
+			}
+			// keep the snipe if the database could not be reached
+			Err(_) => return,
+		}
+
+		println!("[{}] Stopped sniping {}", time(), snipe.username);
+
+		self.snipe = None;
+		self.snipe_token = None;
+	}
+}
+
+/// Gives back `slot` on the snipe for `username`, so that another worker can claim it
+/// once the snipe needs it again.
+fn release_slot(connection: &mut PgConnection, username: &str, slot: i16) -> QueryResult<()> {
+	connection.transaction(|connection| {
+		let released = diesel::delete(schema::snipe_worker::table)
+			.filter(schema::snipe_worker::username.eq(username))
+			.filter(schema::snipe_worker::slot.eq(slot))
+			.execute(connection)?;
+
+		if released > 0 {
+			diesel::update(schema::snipe::table)
+				.filter(schema::snipe::username.eq(username))
+				.set(schema::snipe::count.eq(schema::snipe::count - 1))
+				.execute(connection)?;
+		}
+
+		Ok(())
+	})
 }
 
 impl Connector for Postgres {
@@ -60,6 +127,7 @@ impl Connector for Postgres {
 			.set(schema::name::updating.eq(false))
 			.execute(&mut self.pool.get()?)?;
 
+		diesel::delete(schema::snipe_worker::table).execute(&mut self.pool.get()?)?;
 		diesel::update(schema::snipe::table)
 			.set(schema::snipe::count.eq(0))
 			.execute(&mut self.pool.get()?)?;
@@ -69,45 +137,62 @@ impl Connector for Postgres {
 
 	fn get_accounts<'a>(&self) -> Result<Vec<Account<'a>>, Box<dyn std::error::Error>> {
 		let accounts = schema::account::table
-			.select((schema::account::username, schema::account::password))
-			.load::<AccountData>(&mut self.pool.get()?)?;
+			.select(models::Account::as_select())
+			.load(&mut self.pool.get()?)?;
 
-		Ok(accounts
+		accounts
 			.into_iter()
 			.map(|row| {
 				// we can leak these strings because they will live for the duration of the program
-				Account::new(row.username, row.password)
+				Ok(Account::new(
+					SECRET.decrypt(&row.username)?,
+					SECRET.decrypt(&row.password)?,
+				))
 			})
-			.collect())
+			.collect()
 	}
 
 	fn get_proxies(&self) -> Result<Vec<reqwest::Proxy>, Box<dyn std::error::Error>> {
 		let proxies = schema::proxy::table
-			.select((
-				schema::proxy::address,
-				schema::proxy::port,
-				schema::proxy::username,
-				schema::proxy::password,
-			))
-			.load::<ProxyData>(&mut self.pool.get()?)?;
+			.select(models::Proxy::as_select())
+			.load(&mut self.pool.get()?)?;
 
 		Ok(proxies
 			.into_iter()
 			.filter_map(|row| {
+				if let Err(e) = proxies::validate(
+					&row.address,
+					row.port,
+					row.username.as_deref(),
+					row.password.as_deref(),
+				) {
+					println!(
+						"[{}] Skipping proxy {}: {e}",
+						time(),
+						proxies::url(&row.address, row.port)
+					);
+
+					return None;
+				}
+
 				// we can leak these strings because they will live for the duration of the program
 				match (row.username, row.password) {
 					(Some(username), Some(password)) => Some(
-						reqwest::Proxy::https(format!("{}:{}", row.address, row.port,))
+						reqwest::Proxy::https(proxies::url(&row.address, row.port))
 							.ok()?
 							.basic_auth(&username, &password),
 					),
-					_ => reqwest::Proxy::https(format!("{}:{}", row.address, row.port,)).ok(),
+					_ => reqwest::Proxy::https(proxies::url(&row.address, row.port)).ok(),
 				}
 			})
 			.collect())
 	}
 
 	async fn check_for_snipe(&mut self) -> Option<&Snipe> {
+		if self.snipe.is_some() && self.snipe_refreshed_at.elapsed() >= SNIPE_REFRESH_INTERVAL {
+			self.refresh_snipe();
+		}
+
 		if let Some(snipe) = self.snipe.as_ref() {
 			if let Some(token) = self.snipe_token.as_ref() {
 				if token.expires_at
@@ -145,11 +230,11 @@ impl Connector for Postgres {
 				// we have approximately 15 requests every 30 seconds, per account
 				// we want to spread these out as much as possible across all workers on the snipe
 				//
-				// the worker index is the value of `snipe.count - 1`, out of the total `snipe.needed`
+				// the worker index is the slot held by this worker, out of the total `snipe.needed`
 				// we want to wait for the closest multiple of the correct time. use the `created_at` time
 				// as a base
 
-				let worker_index = snipe.count - 1;
+				let worker_index = self.snipe_slot;
 				let worker_count = snipe.needed;
 
 				// the the offset for the current worker in the 2_000ms period
@@ -184,20 +269,70 @@ impl Connector for Postgres {
 			return self.snipe.as_ref();
 		}
 
-		self.snipe = diesel::update(schema::snipe::table)
-			.filter(schema::snipe::count.lt(schema::snipe::needed))
-			.set((schema::snipe::count.eq(schema::snipe::count + 1),))
-			.returning((
-				schema::snipe::username,
-				schema::snipe::created_at,
-				schema::snipe::needed,
-				schema::snipe::count,
-				schema::snipe::email,
-				schema::snipe::password,
-			))
-			.get_result::<Snipe>(&mut self.pool.get().ok()?)
+		// the worker slot is given back if the credentials cannot be decrypted,
+		// otherwise the snipe would never get all of the workers it needs
+		let claimed = self
+			.pool
+			.get()
+			.ok()?
+			.transaction(|connection| {
+				let mut snipe = diesel::update(schema::snipe::table)
+					.filter(schema::snipe::count.lt(schema::snipe::needed))
+					.set((schema::snipe::count.eq(schema::snipe::count + 1),))
+					.returning(Snipe::as_returning())
+					.get_result(connection)?;
+
+				// fewer than `needed` slots were held, so one of them is always free. slots
+				// above `needed` may still be held by workers that have yet to release them
+				let held = schema::snipe_worker::table
+					.select(schema::snipe_worker::slot)
+					.filter(schema::snipe_worker::username.eq(&snipe.username))
+					.load::<i16>(connection)?;
+				let slot = (0..snipe.needed)
+					.find(|slot| !held.contains(slot))
+					.ok_or(diesel::result::Error::RollbackTransaction)?;
+
+				diesel::insert_into(schema::snipe_worker::table)
+					.values((
+						schema::snipe_worker::username.eq(&snipe.username),
+						schema::snipe_worker::slot.eq(slot),
+					))
+					.execute(connection)?;
+
+				let credentials = SECRET
+					.decrypt(&snipe.email)
+					.and_then(|email| Ok((email, SECRET.decrypt(&snipe.password)?)));
+
+				match credentials {
+					Ok((email, password)) => {
+						snipe.email = email;
+						snipe.password = password;
+
+						Ok((snipe, slot))
+					}
+					Err(e) => {
+						println!(
+							"[{}] Could not decrypt credentials for {}: {e}",
+							time(),
+							snipe.username
+						);
+
+						Err(diesel::result::Error::RollbackTransaction)
+					}
+				}
+			})
 			.ok();
 
+		self.snipe = match claimed {
+			Some((snipe, slot)) => {
+				self.snipe_slot = slot;
+
+				Some(snipe)
+			}
+			None => None,
+		};
+		self.snipe_refreshed_at = Instant::now();
+
 		self.snipe.as_ref()
 	}
 }
@@ -208,40 +343,125 @@ impl Submit for Postgres {
 		username: &str,
 		status: Status,
 	) -> Result<(bool, f64), Box<dyn std::error::Error>> {
+		let mut status = status;
+		let mut source = Source::Checker;
+
 		if let (Some(snipe), Some(token)) = (self.snipe.as_ref(), self.snipe_token.as_ref()) {
 			if snipe.username == username
 				&& status == Status::Available
 				&& sniper::snipe(username, &token.token).await
 			{
+				let connection = &mut self.pool.get()?;
+
 				diesel::delete(schema::snipe::table)
 					.filter(schema::snipe::username.eq(username))
-					.execute(&mut self.pool.get()?)?;
+					.execute(connection)?;
 
 				println!("[{}] Sniped {username}!", time());
+
+				// snipes created before they were owned by a user have nobody to notify
+				if let Some(user_id) = snipe.user_id {
+					if let Err(e) = webhooks::enqueue(
+						connection,
+						Event::SnipeSucceeded,
+						Some(user_id),
+						&serde_json::json!({ "username": username }),
+					) {
+						println!("[{}] Could not queue webhooks for {username}: {e}", time());
+					}
+				}
+
+				// the name now belongs to the sniping account
+				status = Status::Taken;
+				source = Source::Snipe;
+			}
+		}
+
+		let result = self.pool.get()?.transaction(|connection| {
+			// lock the row so the previous status cannot change before the event is recorded
+			let previous = schema::name::table
+				.select(schema::name::status)
+				.filter(schema::name::username.eq(username))
+				.for_update()
+				.get_result::<i16>(connection)?;
+
+			let transition = Status::from(previous).is_transition(status);
+
+			let frequency = diesel::update(schema::name::table)
+				.filter(schema::name::username.eq(username))
+				.set((
+					schema::name::verified_at.eq(diesel::dsl::now),
+					transition.then_some(schema::name::updated_at.eq(diesel::dsl::now)),
+					schema::name::updating.eq(false),
+					schema::name::status.eq(i16::from(status)),
+				))
+				.returning(schema::name::frequency)
+				.get_result::<f64>(connection)?;
+
+			if transition {
+				diesel::insert_into(schema::name_event::table)
+					.values(NewNameEvent::new(
+						username,
+						Status::from(previous),
+						status,
+						source,
+					))
+					.execute(connection)?;
+
+				channels::notify(connection, channels::NAME_STATUS, username)?;
+
+				let event = match status {
+					Status::Available => Some(Event::NameAvailable),
+					Status::Taken => Some(Event::NameTaken),
+					_ => None,
+				};
+
+				if let Some(event) = event {
+					webhooks::enqueue(
+						connection,
+						event,
+						None,
+						&serde_json::json!({
+							"username": username,
+							"previousStatus": Status::from(previous),
+							"status": status,
+							"frequency": frequency,
+						}),
+					)?;
+				}
+
+				// someone else claimed a name that was being sniped, so the snipe missed this drop
+				if status == Status::Taken && source == Source::Checker {
+					let owner = schema::snipe::table
+						.select(schema::snipe::user_id)
+						.filter(schema::snipe::username.eq(username))
+						.get_result::<Option<i32>>(connection)
+						.optional()?
+						.flatten();
+
+					if let Some(user_id) = owner {
+						webhooks::enqueue(
+							connection,
+							Event::SnipeFailed,
+							Some(user_id),
+							&serde_json::json!({ "username": username }),
+						)?;
+					}
+				}
+			}
+
+			Ok::<_, diesel::result::Error>((transition, frequency))
+		})?;
+
+		if result.0 && status == Status::Available {
+			let connection = &mut self.pool.get()?;
+
+			if let Err(e) = alerts::create(connection, username) {
+				println!("[{}] Could not create alerts for {username}: {e}", time());
 			}
 		}
 
-		let status: i16 = status.into();
-		let conditional_update = sql::<Timestamptz>(&format!(
-			"CASE WHEN \"status\" != {status} AND (\"status\" != 5 OR {status} != 2) THEN CURRENT_TIMESTAMP ELSE \"updatedAt\" END",
-		))
-		.into_sql();
-
-		Ok(diesel::update(schema::name::table)
-			.filter(schema::name::username.eq(username))
-			.set((
-				schema::name::verified_at.eq(diesel::dsl::now),
-				schema::name::updated_at.eq(conditional_update),
-				schema::name::updating.eq(false),
-				schema::name::status.eq(status),
-			))
-			.returning((
-				// CURRENT_TIMESTAMP includes microseconds, but only milliseconds are stored in the database
-				// so we need to truncate the timestamp to milliseconds in order to see if the timestamp has changed
-				schema::name::updated_at.eq(date_trunc("milliseconds", diesel::dsl::now)),
-				schema::name::frequency,
-			))
-			.get_result::<(bool, f64)>(&mut self.pool.get()?)?)
+		Ok(result)
 	}
 }
 
@@ -254,7 +474,7 @@ impl HighPrioritySource for Postgres {
 		if self.high.is_empty() {
 			let usernames = schema::name::table
 				.filter(schema::name::updating.eq(false))
-				.filter(schema::name::frequency.ge(15.))
+				.filter(schema::name::frequency.ge(tiers::HIGH))
 				.order((
 					schema::name::verified_at.asc(),
 					schema::name::frequency.desc(),
@@ -286,8 +506,8 @@ impl MediumPrioritySource for Postgres {
 				.filter(schema::name::updating.eq(false))
 				.filter(
 					schema::name::frequency
-						.ge(0.01)
-						.and(schema::name::frequency.lt(15.)),
+						.ge(tiers::MEDIUM)
+						.and(schema::name::frequency.lt(tiers::HIGH)),
 				)
 				.filter(schema::name::status.ne(i16::from(Status::BatchTaken)))
 				.order((
@@ -321,9 +541,9 @@ impl LowPrioritySource for Postgres {
 				.filter(schema::name::updating.eq(false))
 				.filter(schema::name::status.ne(i16::from(Status::BatchTaken)))
 				.filter(
-					schema::name::frequency.lt(0.01).and(
+					schema::name::frequency.lt(tiers::MEDIUM).and(
 						schema::name::frequency
-							.ge(0.001)
+							.ge(tiers::LOW)
 							.or(schema::name::definition.is_not_null()),
 					),
 				)
@@ -2,27 +2,21 @@
 #![allow(clippy::too_many_lines)]
 mod account;
 mod connectors;
+mod notifiers;
+mod popularity;
+mod webhooks;
 
 use account::Error;
 use connectors::prelude::{
 	Connector, HighPrioritySource, LowPrioritySource, MediumPrioritySource, Submit,
 };
 use database::{get_pool, Status};
+use notifiers::{Notification, Notifiers};
 use once_cell::sync::Lazy;
-use reqwest::header;
-use serde::Serialize;
 
 static HTTP: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
 static PROXIES_PER_ACCOUNT: usize = 4;
 
-#[derive(Serialize)]
-struct PushedPayload<'a> {
-	app_key: &'a str,
-	app_secret: &'a str,
-	target_type: &'a str,
-	content: &'a str,
-}
-
 fn time() -> String {
 	chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
 }
@@ -32,8 +26,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	println!("{} Starting...", time());
 	dotenvy::dotenv().ok();
 
-	let app_key = std::env::var("APP_KEY").expect("APP_KEY must be set");
-	let app_secret = std::env::var("APP_SECRET").expect("APP_SECRET must be set");
+	let notifiers = Notifiers::from_env()?;
+
+	metrics::checker::register();
+	metrics::spawn_from_env("CHECKER_METRICS_ADDR", "0.0.0.0:9100");
+
 	let pool = get_pool();
 
 	// use postgres connector
@@ -49,6 +46,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 		(proxies, accounts)
 	};
 
+	// send notifications in the background so that checking never waits on them
+	let notifications = notifiers.spawn();
+	// deliver webhook payloads queued by the checkers
+	tokio::spawn(webhooks::deliver(pool.clone()));
+	// keep the popularity of names up to date for sorting and filtering
+	tokio::spawn(popularity::update(pool.clone()));
+
 	let proxies = proxies.by_ref();
 	let mut tasks = Vec::new();
 
@@ -75,8 +79,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 				pool.clone(),
 				account.get_client().cloned(),
 			);
-			let app_key = app_key.clone();
-			let app_secret = app_secret.clone();
+			let notifications = notifications.clone();
+			let account_label = index.to_string();
+			let tier = match priority {
+				0 => "high",
+				1 => "medium",
+				2 => "low",
+				_ => unreachable!(),
+			};
 
 			async move {
 				'outer: loop {
@@ -94,28 +104,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 							match status {
 								Ok(status) => {
-									println!(
-										"[{}] {} is {} ({})",
-										time(),
-										name,
-										match status {
-											Status::Unknown => "unknown",
-											Status::Available | Status::BatchAvailable =>
-												"available",
-											Status::Taken | Status::BatchTaken => "unavailable",
-											Status::Banned => "banned",
-										},
-										match priority {
-											0 => "high",
-											1 => "medium",
-											2 => "low",
-											_ => unreachable!(),
-										}
-									);
+									let result = match status {
+										Status::Unknown => "unknown",
+										Status::Available | Status::BatchAvailable => "available",
+										Status::Taken | Status::BatchTaken => "unavailable",
+										Status::Banned => "banned",
+									};
+
+									println!("[{}] {} is {} ({})", time(), name, result, tier);
+
+									metrics::checker::CHECKS
+										.with_label_values(&[tier, result])
+										.inc();
+									metrics::checker::ACCOUNT_CHECKS
+										.with_label_values(&[&account_label])
+										.inc();
 
 									break status;
 								}
 								Err(Error::Delay(duration)) => {
+									metrics::checker::RATE_LIMITS
+										.with_label_values(&[&account_label])
+										.inc();
+
 									println!(
 										"[{}] {} is rate limited, waiting {} seconds",
 										time(),
@@ -135,6 +146,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 								Err(Error::Token) => {
 									let seconds = 120;
 
+									metrics::checker::TOKEN_FAILURES
+										.with_label_values(&[&account_label])
+										.inc();
+
 									println!(
 										"[{}] {} could not get token, waiting {} seconds",
 										time(),
@@ -159,18 +174,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 						let (updated, freq) =
 							connector.submit(&name, status).await.unwrap_or((false, 0.));
 
-						if updated && is_available && freq > 10. {
-							HTTP.post("https://api.pushed.co/1/push")
-								.header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
-								.form(&PushedPayload {
-									app_key: &app_key,
-									app_secret: &app_secret,
-									target_type: "app",
-									content: &format!("{name} is now available! ({freq:.2})"),
-								})
-								.send()
-								.await
-								.ok();
+						if updated && is_available {
+							notifications.push(&Notification {
+								username: &name,
+								frequency: freq,
+							});
 						}
 					}
 				}
@@ -0,0 +1,16 @@
+use serde::Deserialize;
+
+use super::{Error, Notification, Notifier};
+use crate::time;
+
+/// Prints notifications to stdout.
+#[derive(Deserialize)]
+pub struct Log;
+
+impl Notifier for Log {
+	async fn send(&self, _notification: &Notification<'_>, message: &str) -> Result<(), Error> {
+		println!("[{}] {message}", time());
+
+		Ok(())
+	}
+}
@@ -0,0 +1,298 @@
+mod log;
+mod pushed;
+mod smtp;
+mod webhook;
+
+use std::{collections::VecDeque, path::Path, sync::Mutex};
+
+use serde::Deserialize;
+use tokio::{
+	sync::mpsc,
+	time::{Duration, Instant},
+};
+
+use crate::time;
+
+/// The file notifiers are read from if `NOTIFIERS_CONFIG` is not set
+const DEFAULT_CONFIG_PATH: &str = "notifiers.toml";
+const DEFAULT_TEMPLATE: &str = "{username} is now available! ({frequency})";
+/// The most notifications waiting to be sent before new ones are dropped
+const QUEUE_SIZE: usize = 256;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("could not read {path}: {source}")]
+	Read {
+		path: String,
+		source: std::io::Error,
+	},
+	#[error("invalid notifier config: {0}")]
+	Config(#[from] toml::de::Error),
+	#[error("invalid email address: {0}")]
+	Address(#[from] lettre::address::AddressError),
+	#[error("could not build email: {0}")]
+	Email(#[from] lettre::error::Error),
+	#[error("could not send email: {0}")]
+	Smtp(#[from] lettre::transport::smtp::Error),
+	#[error("could not send request: {0}")]
+	Request(#[from] reqwest::Error),
+}
+
+/// A name that has just become available.
+pub struct Notification<'a> {
+	pub username: &'a str,
+	pub frequency: f64,
+}
+
+/// Somewhere notifications can be sent.
+pub trait Notifier {
+	/// Sends `message`, which was rendered from `notification`.
+	async fn send(&self, notification: &Notification<'_>, message: &str) -> Result<(), Error>;
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Transport {
+	Log(log::Log),
+	Pushed(pushed::Pushed),
+	Smtp(Box<smtp::Smtp>),
+	Webhook(webhook::Webhook),
+}
+
+impl Notifier for Transport {
+	async fn send(&self, notification: &Notification<'_>, message: &str) -> Result<(), Error> {
+		match self {
+			Transport::Log(notifier) => notifier.send(notification, message).await,
+			Transport::Pushed(notifier) => notifier.send(notification, message).await,
+			Transport::Smtp(notifier) => notifier.send(notification, message).await,
+			Transport::Webhook(notifier) => notifier.send(notification, message).await,
+		}
+	}
+}
+
+impl Transport {
+	fn name(&self) -> &'static str {
+		match self {
+			Transport::Log(..) => "log",
+			Transport::Pushed(..) => "pushed",
+			Transport::Smtp(..) => "smtp",
+			Transport::Webhook(..) => "webhook",
+		}
+	}
+}
+
+#[derive(Deserialize, Clone, Copy)]
+struct RateLimit {
+	/// The most notifications sent within `seconds`
+	count: usize,
+	seconds: u64,
+}
+
+/// A notifier along with the rules for when it is used.
+#[derive(Deserialize)]
+struct Channel {
+	#[serde(flatten)]
+	transport: Transport,
+	/// The lowest frequency a name must have to be sent
+	#[serde(default)]
+	threshold: f64,
+	/// The message sent, where `{username}` and `{frequency}` are replaced
+	#[serde(default = "default_template")]
+	template: String,
+	rate_limit: Option<RateLimit>,
+	/// The times of the notifications sent within the current rate limit window
+	#[serde(skip)]
+	sent: Mutex<VecDeque<Instant>>,
+}
+
+#[derive(Deserialize)]
+struct Config {
+	#[serde(default, rename = "notifier")]
+	channels: Vec<Channel>,
+}
+
+fn default_template() -> String {
+	DEFAULT_TEMPLATE.to_string()
+}
+
+/// Replaces the placeholders in `template` with the values from `notification`.
+fn render(template: &str, notification: &Notification<'_>) -> String {
+	template
+		.replace("{username}", notification.username)
+		.replace("{frequency}", &format!("{:.2}", notification.frequency))
+}
+
+impl Channel {
+	fn new(transport: Transport, threshold: f64) -> Self {
+		Self {
+			transport,
+			threshold,
+			template: default_template(),
+			rate_limit: None,
+			sent: Mutex::default(),
+		}
+	}
+
+	/// Returns `true` if another notification can be sent without exceeding the rate limit,
+	/// recording it as sent.
+	fn acquire(&self) -> bool {
+		let Some(limit) = self.rate_limit else {
+			return true;
+		};
+
+		let now = Instant::now();
+		let window = Duration::from_secs(limit.seconds);
+		let mut sent = self
+			.sent
+			.lock()
+			.expect("rate limit lock to not be poisoned");
+
+		while sent
+			.front()
+			.is_some_and(|at| now.duration_since(*at) >= window)
+		{
+			sent.pop_front();
+		}
+
+		if sent.len() >= limit.count {
+			return false;
+		}
+
+		sent.push_back(now);
+
+		true
+	}
+}
+
+/// Every configured notifier.
+pub struct Notifiers {
+	channels: Vec<Channel>,
+}
+
+/// Queues notifications for [`Notifiers`] to send in the background, so that a slow
+/// notifier never holds up checking.
+#[derive(Clone)]
+pub struct Queue {
+	sender: mpsc::Sender<(String, f64)>,
+}
+
+impl Queue {
+	/// Queues `notification` to be sent, dropping it if the queue is full.
+	pub fn push(&self, notification: &Notification<'_>) {
+		if self
+			.sender
+			.try_send((notification.username.to_string(), notification.frequency))
+			.is_err()
+		{
+			println!(
+				"[{}] Dropped notification for {}, queue is full",
+				time(),
+				notification.username
+			);
+		}
+	}
+}
+
+impl Notifiers {
+	/// Reads the notifiers from the file at `NOTIFIERS_CONFIG`, or `notifiers.toml`.
+	///
+	/// If the file does not exist, notifications are sent to pushed.co when `APP_KEY` and
+	/// `APP_SECRET` are set, and printed otherwise.
+	///
+	/// # Errors
+	/// Returns an error if the file could not be read or is not a valid config.
+	pub fn from_env() -> Result<Self, Error> {
+		let path = std::env::var("NOTIFIERS_CONFIG");
+		let explicit = path.is_ok();
+		let path = path.unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+
+		if !explicit && !Path::new(&path).exists() {
+			return Ok(Self::fallback());
+		}
+
+		let config = std::fs::read_to_string(&path).map_err(|source| Error::Read {
+			path: path.clone(),
+			source,
+		})?;
+		let config = toml::from_str::<Config>(&config)?;
+
+		println!(
+			"{} Loaded {} notifier(s) from {path}",
+			time(),
+			config.channels.len()
+		);
+
+		Ok(Self {
+			channels: config.channels,
+		})
+	}
+
+	fn fallback() -> Self {
+		let transport = match (std::env::var("APP_KEY"), std::env::var("APP_SECRET")) {
+			(Ok(app_key), Ok(app_secret)) => {
+				Transport::Pushed(pushed::Pushed::new(app_key, app_secret))
+			}
+			_ => Transport::Log(log::Log),
+		};
+
+		println!(
+			"{} No notifier config found, sending notifications to {}",
+			time(),
+			transport.name()
+		);
+
+		Self {
+			channels: vec![Channel::new(transport, 10.)],
+		}
+	}
+
+	/// Spawns a task that sends the notifications pushed to the returned [`Queue`]
+	/// one at a time.
+	pub fn spawn(self) -> Queue {
+		let (sender, mut receiver) = mpsc::channel::<(String, f64)>(QUEUE_SIZE);
+
+		tokio::spawn(async move {
+			while let Some((username, frequency)) = receiver.recv().await {
+				self.notify(&Notification {
+					username: &username,
+					frequency,
+				})
+				.await;
+			}
+		});
+
+		Queue { sender }
+	}
+
+	/// Sends `notification` to every notifier whose threshold it meets and
+	/// whose rate limit has not been reached.
+	pub async fn notify(&self, notification: &Notification<'_>) {
+		for channel in &self.channels {
+			if notification.frequency < channel.threshold {
+				continue;
+			}
+
+			if !channel.acquire() {
+				println!(
+					"[{}] Skipped {} notification for {}, rate limit reached",
+					time(),
+					channel.transport.name(),
+					notification.username
+				);
+
+				continue;
+			}
+
+			let message = render(&channel.template, notification);
+
+			if let Err(e) = channel.transport.send(notification, &message).await {
+				println!(
+					"[{}] Could not send {} notification for {}: {e}",
+					time(),
+					channel.transport.name(),
+					notification.username
+				);
+			}
+		}
+	}
+}
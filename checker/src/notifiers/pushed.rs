@@ -0,0 +1,54 @@
+use reqwest::header;
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Notification, Notifier};
+use crate::HTTP;
+
+/// Sends push notifications through pushed.co.
+#[derive(Deserialize)]
+pub struct Pushed {
+	app_key: String,
+	app_secret: String,
+	#[serde(default = "default_target_type")]
+	target_type: String,
+}
+
+#[derive(Serialize)]
+struct PushedPayload<'a> {
+	app_key: &'a str,
+	app_secret: &'a str,
+	target_type: &'a str,
+	content: &'a str,
+}
+
+fn default_target_type() -> String {
+	"app".to_string()
+}
+
+impl Pushed {
+	pub fn new(app_key: String, app_secret: String) -> Self {
+		Self {
+			app_key,
+			app_secret,
+			target_type: default_target_type(),
+		}
+	}
+}
+
+impl Notifier for Pushed {
+	async fn send(&self, _notification: &Notification<'_>, message: &str) -> Result<(), Error> {
+		HTTP.post("https://api.pushed.co/1/push")
+			.header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+			.form(&PushedPayload {
+				app_key: &self.app_key,
+				app_secret: &self.app_secret,
+				target_type: &self.target_type,
+				content: message,
+			})
+			.send()
+			.await?
+			.error_for_status()?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,97 @@
+use lettre::{
+	message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+	AsyncTransport, Message, Tokio1Executor,
+};
+use serde::Deserialize;
+
+use super::{render, Error, Notification, Notifier};
+
+/// Sends notifications as emails through an SMTP server.
+#[derive(Deserialize)]
+#[serde(try_from = "SmtpConfig")]
+pub struct Smtp {
+	transport: AsyncSmtpTransport<Tokio1Executor>,
+	from: Mailbox,
+	to: Vec<Mailbox>,
+	subject: String,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+enum Security {
+	/// Sends everything in plaintext, which should only be used for local servers
+	None,
+	#[default]
+	StartTls,
+	Tls,
+}
+
+#[derive(Deserialize)]
+struct SmtpConfig {
+	host: String,
+	port: Option<u16>,
+	#[serde(default)]
+	tls: Security,
+	username: Option<String>,
+	password: Option<String>,
+	from: String,
+	to: Vec<String>,
+	/// The subject of the email, where `{username}` and `{frequency}` are replaced
+	#[serde(default = "default_subject")]
+	subject: String,
+}
+
+fn default_subject() -> String {
+	"{username} is now available".to_string()
+}
+
+impl TryFrom<SmtpConfig> for Smtp {
+	type Error = Error;
+
+	fn try_from(config: SmtpConfig) -> Result<Self, Self::Error> {
+		let mut transport = match config.tls {
+			Security::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+			Security::StartTls => {
+				AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+			}
+			Security::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+		};
+
+		if let Some(port) = config.port {
+			transport = transport.port(port);
+		}
+
+		if let (Some(username), Some(password)) = (config.username, config.password) {
+			transport = transport.credentials(Credentials::new(username, password));
+		}
+
+		Ok(Self {
+			transport: transport.build(),
+			from: config.from.parse()?,
+			to: config
+				.to
+				.iter()
+				.map(|to| to.parse())
+				.collect::<Result<_, _>>()?,
+			subject: config.subject,
+		})
+	}
+}
+
+impl Notifier for Smtp {
+	async fn send(&self, notification: &Notification<'_>, message: &str) -> Result<(), Error> {
+		let mut email = Message::builder()
+			.from(self.from.clone())
+			.subject(render(&self.subject, notification));
+
+		for to in &self.to {
+			email = email.to(to.clone());
+		}
+
+		self.transport
+			.send(email.body(message.to_string())?)
+			.await?;
+
+		Ok(())
+	}
+}
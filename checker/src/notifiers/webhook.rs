@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Notification, Notifier};
+use crate::HTTP;
+
+/// Posts notifications as JSON to a URL.
+#[derive(Deserialize)]
+pub struct Webhook {
+	url: String,
+	/// Extra headers sent with every request, such as an authorization token
+	#[serde(default)]
+	headers: HashMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+	username: &'a str,
+	frequency: f64,
+	message: &'a str,
+}
+
+impl Notifier for Webhook {
+	async fn send(&self, notification: &Notification<'_>, message: &str) -> Result<(), Error> {
+		let mut request = HTTP.post(&self.url).json(&WebhookPayload {
+			username: notification.username,
+			frequency: notification.frequency,
+			message,
+		});
+
+		for (name, value) in &self.headers {
+			request = request.header(name, value);
+		}
+
+		request.send().await?.error_for_status()?;
+
+		Ok(())
+	}
+}
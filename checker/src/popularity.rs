@@ -0,0 +1,32 @@
+use database::{popularity, PostgresPool};
+use tokio::time::Duration;
+
+use crate::time;
+
+/// How often the popularity of every name is recomputed
+const INTERVAL: Duration = Duration::from_mins(10);
+/// The most names updated in a single statement
+const BATCH_SIZE: i64 = 5000;
+
+/// Recomputes the popularity of every name every [`INTERVAL`], forever.
+pub async fn update(pool: PostgresPool) {
+	loop {
+		let pool = pool.clone();
+		// the update scans every name, so it is kept off the runtime's worker threads
+		let result = tokio::task::spawn_blocking(move || {
+			let mut connection = pool.get().map_err(|e| e.to_string())?;
+
+			popularity::update(&mut connection, BATCH_SIZE).map_err(|e| e.to_string())
+		})
+		.await
+		.map_err(|e| e.to_string())
+		.and_then(|result| result);
+
+		match result {
+			Ok(updated) => println!("[{}] Updated the popularity of {updated} name(s)", time()),
+			Err(e) => println!("[{}] Could not update popularity: {e}", time()),
+		}
+
+		tokio::time::sleep(INTERVAL).await;
+	}
+}
@@ -0,0 +1,237 @@
+use std::{net::IpAddr, sync::Arc};
+
+use database::{models::Webhook, schema, webhooks, PostgresPool};
+use diesel::{
+	Connection, ExpressionMethods, PgConnection, QueryDsl, Queryable, RunQueryDsl, Selectable,
+	SelectableHelper,
+};
+use reqwest::{
+	dns::{Addrs, Name, Resolve, Resolving},
+	header,
+	redirect::Policy,
+};
+use tokio::time::Duration;
+
+use crate::{connectors::sources::postgres::SECRET, time};
+
+/// How often the queue is checked for deliveries that are due
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How long a claimed delivery is hidden from other workers before it is retried
+const LEASE: Duration = Duration::from_mins(1);
+/// The most deliveries claimed at once
+const BATCH_SIZE: i64 = 20;
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::webhook_delivery, check_for_backend(diesel::pg::Pg))]
+struct Delivery {
+	id: i64,
+	webhook_id: i32,
+	event: String,
+	payload: serde_json::Value,
+	attempts: i16,
+}
+
+/// Claims the deliveries that are due, so that no other worker sends them
+/// until the lease expires.
+fn claim(connection: &mut PgConnection) -> diesel::QueryResult<Vec<Delivery>> {
+	let active = schema::webhook::table
+		.select(schema::webhook::id)
+		.filter(schema::webhook::active.eq(true));
+
+	connection.transaction(|connection| {
+		let due = schema::webhook_delivery::table
+			.select(schema::webhook_delivery::id)
+			.filter(
+				schema::webhook_delivery::status.eq(i16::from(webhooks::DeliveryStatus::Pending)),
+			)
+			.filter(schema::webhook_delivery::next_attempt_at.le(diesel::dsl::now))
+			.filter(schema::webhook_delivery::webhook_id.eq_any(active))
+			.order(schema::webhook_delivery::next_attempt_at.asc())
+			.limit(BATCH_SIZE)
+			.for_update()
+			.skip_locked()
+			.load::<i64>(connection)?;
+
+		diesel::update(schema::webhook_delivery::table)
+			.filter(schema::webhook_delivery::id.eq_any(due))
+			.set(
+				schema::webhook_delivery::next_attempt_at.eq(chrono::Utc::now()
+					+ chrono::Duration::from_std(LEASE).expect("lease to be less than i64::MAX")),
+			)
+			.returning(Delivery::as_returning())
+			.get_results(connection)
+	})
+}
+
+/// Resolves the hosts of webhooks, leaving out every address that is not public,
+/// since a domain can be changed to point to a private address after it was checked.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+	fn resolve(&self, name: Name) -> Resolving {
+		Box::pin(async move {
+			let addresses = tokio::net::lookup_host((name.as_str(), 0))
+				.await?
+				.filter(|address| webhooks::is_public(address.ip()))
+				.collect::<Vec<_>>();
+
+			if addresses.is_empty() {
+				return Err(
+					format!("{} does not resolve to a public address", name.as_str()).into(),
+				);
+			}
+
+			Ok(Box::new(addresses.into_iter()) as Addrs)
+		})
+	}
+}
+
+/// Returns `true` if the host of `url` is an address that is not public. Addresses
+/// in the url are never resolved, so they are not checked by [`PublicResolver`].
+fn is_private_address(url: &str) -> bool {
+	reqwest::Url::parse(url).is_ok_and(|url| {
+		url.host_str()
+			.map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+			.and_then(|host| host.parse::<IpAddr>().ok())
+			.is_some_and(|ip| !webhooks::is_public(ip))
+	})
+}
+
+/// Sends a delivery to its webhook, returning the status code of the response
+/// and an error if it was not successful.
+async fn send(
+	client: &reqwest::Client,
+	webhook: &Webhook,
+	delivery: &Delivery,
+) -> (Option<i32>, Option<String>) {
+	let secret = match SECRET.decrypt(&webhook.secret) {
+		Ok(secret) => secret,
+		Err(e) => return (None, Some(format!("could not decrypt secret: {e}"))),
+	};
+
+	if is_private_address(&webhook.url) {
+		return (None, Some("url points to a private address".to_string()));
+	}
+
+	let body = delivery.payload.to_string();
+	let timestamp = chrono::Utc::now().timestamp();
+
+	let response = client
+		.post(&webhook.url)
+		.header(header::CONTENT_TYPE, "application/json")
+		.header("X-Webhook-Event", &delivery.event)
+		.header("X-Webhook-Delivery", delivery.id)
+		.header("X-Webhook-Timestamp", timestamp)
+		.header(
+			"X-Webhook-Signature",
+			webhooks::sign(&secret, timestamp, &body),
+		)
+		.body(body)
+		.send()
+		.await;
+
+	match response {
+		Ok(response) => {
+			let status = response.status();
+
+			if status.is_success() {
+				(Some(i32::from(status.as_u16())), None)
+			} else {
+				(
+					Some(i32::from(status.as_u16())),
+					Some(format!("unexpected status {status}")),
+				)
+			}
+		}
+		Err(e) => (None, Some(e.to_string())),
+	}
+}
+
+/// Delivers queued webhook payloads forever, retrying failed deliveries with
+/// exponential backoff until they run out of attempts.
+pub async fn deliver(pool: PostgresPool) {
+	let client = reqwest::Client::builder()
+		.timeout(Duration::from_secs(10))
+		.user_agent(concat!("minecraft-backend/", env!("CARGO_PKG_VERSION")))
+		.dns_resolver(Arc::new(PublicResolver))
+		// a redirect could point to a private address
+		.redirect(Policy::none())
+		.build()
+		.expect("client to be valid");
+
+	loop {
+		let claimed = pool
+			.get()
+			.map_err(|e| e.to_string())
+			.and_then(|mut connection| {
+				let deliveries = claim(&mut connection).map_err(|e| e.to_string())?;
+				let targets = schema::webhook::table
+					.filter(
+						schema::webhook::id
+							.eq_any(deliveries.iter().map(|delivery| delivery.webhook_id)),
+					)
+					.load::<Webhook>(&mut connection)
+					.map_err(|e| e.to_string())?;
+
+				Ok((deliveries, targets))
+			});
+
+		let (deliveries, targets) = match claimed {
+			Ok(claimed) => claimed,
+			Err(e) => {
+				println!("[{}] Could not claim webhook deliveries: {e}", time());
+				tokio::time::sleep(POLL_INTERVAL).await;
+
+				continue;
+			}
+		};
+
+		if deliveries.is_empty() {
+			tokio::time::sleep(POLL_INTERVAL).await;
+
+			continue;
+		}
+
+		for delivery in deliveries {
+			let Some(webhook) = targets
+				.iter()
+				.find(|webhook| webhook.id == delivery.webhook_id)
+			else {
+				continue;
+			};
+
+			let (response_status, error) = send(&client, webhook, &delivery).await;
+
+			if let Some(error) = &error {
+				println!(
+					"[{}] Webhook delivery {} to {} failed: {error}",
+					time(),
+					delivery.id,
+					webhook.url
+				);
+			}
+
+			let recorded = pool
+				.get()
+				.map_err(|e| e.to_string())
+				.and_then(|mut connection| {
+					webhooks::record_attempt(
+						&mut connection,
+						delivery.id,
+						delivery.attempts,
+						response_status,
+						error.as_deref(),
+					)
+					.map_err(|e| e.to_string())
+				});
+
+			if let Err(e) = recorded {
+				println!(
+					"[{}] Could not record webhook delivery {}: {e}",
+					time(),
+					delivery.id
+				);
+			}
+		}
+	}
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use diesel::{
+	ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+};
+
+use crate::{
+	channels,
+	filter::NameFilter,
+	models::{Alert, NewAlert, SavedSearch},
+	schema, Status,
+};
+
+/// The number of saved searches loaded at a time
+const BATCH_SIZE: i64 = 500;
+
+/// Alerts every user with a saved search matching `username`, which has just become available.
+///
+/// A user is alerted at most once per drop, even if several of their searches match
+/// or this is called more than once for the same drop.
+pub fn create(connection: &mut PgConnection, username: &str) -> QueryResult<Vec<Alert>> {
+	// the drop is identified by the event that recorded the name becoming available
+	let Some(name_event_id) = schema::name_event::table
+		.select(schema::name_event::id)
+		.filter(schema::name_event::username.eq(username))
+		.filter(schema::name_event::new_status.eq(i16::from(Status::Available)))
+		.order(schema::name_event::id.desc())
+		.first::<i64>(connection)
+		.optional()?
+	else {
+		return Ok(Vec::new());
+	};
+
+	let mut alerts = Vec::new();
+	let mut last_id = 0;
+
+	loop {
+		let searches = schema::saved_search::table
+			.filter(schema::saved_search::alerts.eq(true))
+			.filter(schema::saved_search::id.gt(last_id))
+			.order(schema::saved_search::id.asc())
+			.limit(BATCH_SIZE)
+			.load::<SavedSearch>(connection)?;
+
+		let Some(last) = searches.last() else {
+			break;
+		};
+
+		last_id = last.id;
+
+		let is_last_batch = searches.len() < BATCH_SIZE as usize;
+
+		// searches with the same filter match the same names, so each filter is only checked once,
+		// apart from filters depending on the likes of a user which are checked once per user
+		let mut groups = HashMap::<String, (NameFilter, i32, Vec<(i32, i32)>)>::new();
+
+		for search in searches {
+			// filters are validated when they are saved, so this only fails if the format changes
+			let Ok(filter) = serde_json::from_value::<NameFilter>(search.filter) else {
+				continue;
+			};
+
+			let filter = filter.normalized();
+			let key = serde_json::to_string(&filter).expect("filter to always be serializable");
+			let key = if filter.is_personal() {
+				format!("{}:{key}", search.user_id)
+			} else {
+				key
+			};
+
+			groups
+				.entry(key)
+				.or_insert_with(|| (filter, search.user_id, Vec::new()))
+				.2
+				.push((search.id, search.user_id));
+		}
+
+		let mut matched = Vec::new();
+
+		for (filter, user_id, searches) in groups.into_values() {
+			let matches = diesel::select(diesel::dsl::exists(
+				filter
+					.query(user_id)
+					.filter(schema::name::username.eq(username)),
+			))
+			.get_result::<bool>(connection)?;
+
+			if matches {
+				matched.extend(searches);
+			}
+		}
+
+		if !matched.is_empty() {
+			// the earliest matching search of each user is the one their alert refers to
+			matched.sort_unstable();
+
+			let new_alerts = matched
+				.into_iter()
+				.map(|(id, user_id)| NewAlert {
+					user_id,
+					saved_search_id: Some(id),
+					username,
+					name_event_id,
+				})
+				.collect::<Vec<_>>();
+
+			let inserted = diesel::insert_into(schema::alert::table)
+				.values(&new_alerts)
+				.on_conflict((schema::alert::user_id, schema::alert::name_event_id))
+				.do_nothing()
+				.get_results::<Alert>(connection)?;
+
+			for alert in &inserted {
+				channels::notify(connection, channels::ALERT, &alert.id.to_string())?;
+			}
+
+			alerts.extend(inserted);
+		}
+
+		if is_last_batch {
+			break;
+		}
+	}
+
+	Ok(alerts)
+}
@@ -0,0 +1,17 @@
+use diesel::{sql_types::Text, PgConnection, QueryResult, RunQueryDsl};
+
+/// Notified with the username whenever the checker sees a name change status.
+pub const NAME_STATUS: &str = "name_status";
+/// Notified with the id of every new alert.
+pub const ALERT: &str = "alert";
+
+/// Sends `payload` to every connection listening on `channel`.
+///
+/// When called inside a transaction, the notification is only delivered once it commits.
+pub fn notify(connection: &mut PgConnection, channel: &str, payload: &str) -> QueryResult<()> {
+	diesel::sql_query("SELECT pg_notify($1, $2)")
+		.bind::<Text, _>(channel)
+		.bind::<Text, _>(payload)
+		.execute(connection)
+		.map(|_| ())
+}
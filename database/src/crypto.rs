@@ -0,0 +1,96 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+	aead::{Aead, AeadCore, KeyInit, OsRng},
+	ChaCha20Poly1305, Key, Nonce,
+};
+use sha2::{Digest, Sha256};
+
+/// Marks a value as encrypted, so that plaintext values written before
+/// encryption was introduced can still be read until they are rotated.
+const PREFIX: &str = "enc:v1:";
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("encrypted value is malformed")]
+	Malformed,
+	#[error("encrypted value could not be decrypted with any known secret")]
+	Decryption,
+}
+
+/// Encrypts and decrypts stored credentials.
+///
+/// Values are always encrypted with the current secret. When rotating secrets,
+/// the previous secret is still used to decrypt values that have not yet been
+/// re-encrypted.
+#[derive(Clone)]
+pub struct Secret {
+	current: ChaCha20Poly1305,
+	previous: Option<ChaCha20Poly1305>,
+}
+
+fn cipher(secret: &str) -> ChaCha20Poly1305 {
+	let key = Sha256::digest(secret.as_bytes());
+
+	ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+impl Secret {
+	pub fn new(current: &str, previous: Option<&str>) -> Self {
+		Self {
+			current: cipher(current),
+			previous: previous.map(cipher),
+		}
+	}
+
+	/// Creates a secret from the `SECRET` and optional `SECRET_PREVIOUS` environment variables.
+	pub fn from_env() -> Self {
+		let current = std::env::var("SECRET").expect("environment variable SECRET not found");
+		let previous = std::env::var("SECRET_PREVIOUS").ok();
+
+		Self::new(&current, previous.as_deref())
+	}
+
+	pub fn encrypt(&self, plaintext: &str) -> String {
+		let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+		let mut bytes = self
+			.current
+			.encrypt(&nonce, plaintext.as_bytes())
+			.expect("encryption to only fail for messages larger than 256GiB");
+
+		bytes.splice(0..0, nonce);
+
+		format!("{PREFIX}{}", STANDARD.encode(bytes))
+	}
+
+	/// # Errors
+	/// - `Error::Malformed` if the value is not valid base64 or is too short
+	/// - `Error::Decryption` if neither the current nor previous secret can decrypt the value
+	pub fn decrypt(&self, value: &str) -> Result<String, Error> {
+		let Some(encoded) = value.strip_prefix(PREFIX) else {
+			// the value was written before encryption was introduced
+			return Ok(value.to_string());
+		};
+
+		let bytes = STANDARD.decode(encoded).map_err(|_| Error::Malformed)?;
+
+		if bytes.len() < NONCE_LENGTH {
+			return Err(Error::Malformed);
+		}
+
+		let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+		let nonce = Nonce::from_slice(nonce);
+
+		let plaintext = match self.current.decrypt(nonce, ciphertext) {
+			Ok(plaintext) => plaintext,
+			Err(_) => self
+				.previous
+				.as_ref()
+				.ok_or(Error::Decryption)?
+				.decrypt(nonce, ciphertext)
+				.map_err(|_| Error::Decryption)?,
+		};
+
+		String::from_utf8(plaintext).map_err(|_| Error::Malformed)
+	}
+}
@@ -0,0 +1,54 @@
+use diesel::{
+	pg::Pg,
+	query_builder::{AstPass, Query, QueryFragment, QueryId},
+	sql_types::Text,
+	PgConnection, QueryResult, RunQueryDsl,
+};
+
+/// Wraps a query in `EXPLAIN`, returning one row of text per line of the plan.
+pub struct Explain<Q>(pub Q);
+
+impl<Q> QueryId for Explain<Q> {
+	type QueryId = ();
+
+	const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<Q> Query for Explain<Q> {
+	type SqlType = Text;
+}
+
+impl<Q> QueryFragment<Pg> for Explain<Q>
+where
+	Q: QueryFragment<Pg>,
+{
+	fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+		out.push_sql("EXPLAIN ");
+		self.0.walk_ast(out.reborrow())
+	}
+}
+
+impl<Q> RunQueryDsl<PgConnection> for Explain<Q> {}
+
+/// Returns the planner's estimate of the number of rows `query` will return.
+///
+/// This is much cheaper than a `COUNT(*)` over large tables, but it is only as
+/// accurate as the table statistics gathered by `ANALYZE`.
+pub fn estimate_rows<Q>(query: Q, connection: &mut PgConnection) -> QueryResult<i64>
+where
+	Q: QueryFragment<Pg>,
+{
+	// the first line of the plan describes the top-level node, e.g.
+	// "Seq Scan on name  (cost=0.00..35.50 rows=2550 width=32)"
+	let plan = Explain(query).get_result::<String>(connection)?;
+
+	Ok(plan
+		.split_once("rows=")
+		.and_then(|(_, rest)| {
+			rest.split(|c: char| !c.is_ascii_digit())
+				.next()?
+				.parse()
+				.ok()
+		})
+		.unwrap_or(0))
+}
@@ -0,0 +1,606 @@
+use diesel::{prelude::*, sql_types::Text};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	functions::{definition_query, RegexExpressionMethods, TextSearchExpressionMethods},
+	models::Name,
+	schema, Status,
+};
+
+/// The longest pattern accepted, to keep the cost of matching every name bounded
+pub const MAX_PATTERN_LENGTH: usize = 64;
+/// The longest search over definitions accepted
+pub const MAX_DEFINITION_QUERY_LENGTH: usize = 256;
+
+const CONSONANTS: &str = "[bcdfghjklmnpqrstvwxyz]";
+const VOWELS: &str = "[aeiou]";
+
+/// The names table joined with the likes of a single user.
+type NamesSource = diesel::dsl::LeftJoinOn<
+	schema::name::table,
+	schema::like::table,
+	diesel::dsl::And<
+		diesel::dsl::Eq<schema::like::username, schema::name::username>,
+		diesel::dsl::Eq<schema::like::user_id, i32>,
+	>,
+>;
+
+pub type BoxedNames<'a> = diesel::dsl::IntoBoxed<'a, NamesSource, diesel::pg::Pg>;
+
+/// Whether a name is liked by the user whose likes were joined, such as in [`NameFilter::query`].
+pub fn liked() -> diesel::dsl::IsNotNull<diesel::dsl::Nullable<schema::like::username>> {
+	schema::like::username.nullable().is_not_null()
+}
+
+/// The lowest frequency of a name with the `common` tag
+const COMMON_FREQUENCY: f64 = 0.5;
+/// The longest name with the `short` tag
+const SHORT_LENGTH: i32 = 7;
+
+/// What a tag in [`NameFilter::tags`] filters for, shared by [`NameFilter::query`] and
+/// [`NameFilter::matches`] so that both select the same names.
+enum Tag<'a> {
+	Common,
+	Short,
+	Letters,
+	Digits,
+	Underscore,
+	Liked,
+	/// Any status other than [`Status::BatchAvailable`]
+	Taken,
+	Banned,
+	/// A tag that every name must have
+	Other(&'a str),
+}
+
+impl<'a> From<&'a str> for Tag<'a> {
+	fn from(tag: &'a str) -> Self {
+		match tag {
+			"common" => Tag::Common,
+			"short" => Tag::Short,
+			"letters" => Tag::Letters,
+			"digits" => Tag::Digits,
+			"underscore" => Tag::Underscore,
+			"liked" => Tag::Liked,
+			"taken" => Tag::Taken,
+			"banned" => Tag::Banned,
+			tag => Tag::Other(tag),
+		}
+	}
+}
+
+/// A filter that is well-formed but cannot be used.
+#[derive(Debug, thiserror::Error)]
+#[error("{field}: {message}")]
+pub struct Error {
+	pub field: &'static str,
+	pub message: &'static str,
+}
+
+/// An inclusive range, where either bound can be omitted.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct Range<T> {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub min: Option<T>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max: Option<T>,
+}
+
+impl<T: PartialOrd + Copy> Range<T> {
+	fn contains(&self, value: T) -> bool {
+		self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+	}
+
+	/// Rejects a range that cannot match anything.
+	///
+	/// # Errors
+	/// Returns an error naming `field` if `min` is greater than `max`.
+	pub fn validate(&self, field: &'static str) -> Result<(), Error> {
+		match (self.min, self.max) {
+			(Some(min), Some(max)) if min > max => Err(Error {
+				field,
+				message: "min must not be greater than max",
+			}),
+			_ => Ok(()),
+		}
+	}
+}
+
+/// A pattern that usernames must match, compiled to a POSIX regular expression.
+///
+/// Patterns are written as either `{ "wildcard": "..." }` or `{ "regex": "..." }`.
+///
+/// Wildcards match the whole username, where `?` is any character, `*` is any number
+/// of characters, `C` is a consonant, `V` is a vowel and `#` is a digit. Any other
+/// letter, digit or underscore matches itself.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "RawPattern", into = "RawPattern")]
+pub struct Pattern {
+	raw: RawPattern,
+	regex: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum RawPattern {
+	Wildcard(String),
+	Regex(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PatternError {
+	#[error("pattern must be at most {MAX_PATTERN_LENGTH} characters")]
+	Length,
+	#[error("wildcard contains an unsupported character: {0}")]
+	Character(char),
+	#[error("regex must not contain back references")]
+	BackReference,
+}
+
+impl TryFrom<RawPattern> for Pattern {
+	type Error = PatternError;
+
+	fn try_from(raw: RawPattern) -> Result<Self, Self::Error> {
+		let regex = match &raw {
+			RawPattern::Wildcard(wildcard) => compile_wildcard(wildcard)?,
+			RawPattern::Regex(regex) => check_regex(regex)?.to_string(),
+		};
+
+		Ok(Self { raw, regex })
+	}
+}
+
+impl From<Pattern> for RawPattern {
+	fn from(pattern: Pattern) -> Self {
+		pattern.raw
+	}
+}
+
+fn compile_wildcard(wildcard: &str) -> Result<String, PatternError> {
+	if wildcard.len() > MAX_PATTERN_LENGTH {
+		return Err(PatternError::Length);
+	}
+
+	let mut regex = String::from("^");
+
+	for c in wildcard.chars() {
+		match c {
+			'?' => regex.push('.'),
+			'*' => regex.push_str(".*"),
+			'C' => regex.push_str(CONSONANTS),
+			'V' => regex.push_str(VOWELS),
+			'#' => regex.push_str("[0-9]"),
+			'a'..='z' | '0'..='9' | '_' => regex.push(c),
+			c => return Err(PatternError::Character(c)),
+		}
+	}
+
+	regex.push('$');
+
+	Ok(regex)
+}
+
+fn check_regex(regex: &str) -> Result<&str, PatternError> {
+	if regex.len() > MAX_PATTERN_LENGTH {
+		return Err(PatternError::Length);
+	}
+
+	// back references are the only construct that can make matching exponential
+	if regex
+		.as_bytes()
+		.windows(2)
+		.any(|pair| pair[0] == b'\\' && pair[1].is_ascii_digit())
+	{
+		return Err(PatternError::BackReference);
+	}
+
+	Ok(regex)
+}
+
+impl Pattern {
+	/// Returns the POSIX regular expression the pattern was compiled to.
+	pub fn as_regex(&self) -> &str {
+		&self.regex
+	}
+
+	/// Returns `true` if Postgres accepts the regular expression, so that an invalid
+	/// pattern can be rejected before it fails a query.
+	pub fn is_valid(&self, connection: &mut PgConnection) -> bool {
+		diesel::select("".into_sql::<Text>().regex_match(self.regex.as_str()))
+			.get_result::<bool>(connection)
+			.is_ok()
+	}
+}
+
+/// A tag accepted in [`NameFilter::tags`] that is a shorthand for another filter
+/// rather than a tag stored on names.
+#[derive(Serialize, Clone, Copy)]
+pub struct PseudoTag {
+	pub tag: &'static str,
+	pub description: &'static str,
+}
+
+/// Every pseudo-tag handled by [`NameFilter::query`].
+pub const PSEUDO_TAGS: &[PseudoTag] = &[
+	PseudoTag {
+		tag: "new",
+		description: "Names updated within the last 24 hours",
+	},
+	PseudoTag {
+		tag: "common",
+		description: "Names with a frequency of at least 0.5",
+	},
+	PseudoTag {
+		tag: "short",
+		description: "Names with at most 7 characters",
+	},
+	PseudoTag {
+		tag: "letters",
+		description: "Names made up of only letters",
+	},
+	PseudoTag {
+		tag: "digits",
+		description: "Names that contain a digit",
+	},
+	PseudoTag {
+		tag: "underscore",
+		description: "Names that contain an underscore",
+	},
+	PseudoTag {
+		tag: "liked",
+		description: "Names you have liked",
+	},
+	PseudoTag {
+		tag: "taken",
+		description:
+			"Names of every status except batch-available, instead of only available names",
+	},
+	PseudoTag {
+		tag: "banned",
+		description: "Names that are banned, instead of only available names",
+	},
+];
+
+/// The filters that select which names are included in a listing or saved search.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NameFilter {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub search: Option<String>,
+	/// Words that the definitions of every name must mention, such as `fire or animal`.
+	/// Quoted phrases, `or` and `-` to exclude a word are supported.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub definition_query: Option<String>,
+	/// A wildcard or regular expression that usernames must match
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pattern: Option<Pattern>,
+	/// Shorthands for common filters, as well as tags that every name must have.
+	/// Prefer the explicit filters below, which these are equivalent to.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tags: Option<Vec<String>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub length: Option<Range<i32>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub frequency: Option<Range<f64>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub popularity: Option<Range<f64>>,
+	/// The statuses to include, which defaults to only available names
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub statuses: Option<Vec<Status>>,
+	/// Tags that every name must have
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub include_tags: Option<Vec<String>>,
+	/// Tags that no name can have
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub exclude_tags: Option<Vec<String>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub from: Option<chrono::NaiveDateTime>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub to: Option<chrono::NaiveDateTime>,
+}
+
+impl NameFilter {
+	/// Rejects filters that are well-formed but cannot match anything.
+	///
+	/// # Errors
+	/// Returns an error describing the first invalid field.
+	pub fn validate(&self) -> Result<(), Error> {
+		if let Some(query) = &self.definition_query {
+			if query.trim().is_empty() || query.len() > MAX_DEFINITION_QUERY_LENGTH {
+				return Err(Error {
+					field: "definitionQuery",
+					message: "must contain between 1 and 256 characters",
+				});
+			}
+		}
+
+		if let Some(length) = &self.length {
+			length.validate("length")?;
+		}
+
+		if let Some(frequency) = &self.frequency {
+			frequency.validate("frequency")?;
+		}
+
+		if let Some(popularity) = &self.popularity {
+			popularity.validate("popularity")?;
+		}
+
+		if self.statuses.as_ref().is_some_and(Vec::is_empty) {
+			return Err(Error {
+				field: "statuses",
+				message: "must contain at least one status",
+			});
+		}
+
+		if let (Some(from), Some(to)) = (self.from, self.to) {
+			if from > to {
+				return Err(Error {
+					field: "from",
+					message: "must not be after to",
+				});
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns `true` if the names matched depend on who is asking, such as with the `liked` tag.
+	pub fn is_personal(&self) -> bool {
+		self.tags
+			.iter()
+			.flatten()
+			.any(|tag| matches!(Tag::from(tag.as_str()), Tag::Liked))
+	}
+
+	/// Returns an equivalent filter with its lists sorted and deduplicated, so that
+	/// filters matching the same names serialize the same way.
+	pub fn normalized(&self) -> Self {
+		fn normalize<T: Ord>(mut list: Vec<T>) -> Option<Vec<T>> {
+			list.sort_unstable();
+			list.dedup();
+
+			(!list.is_empty()).then_some(list)
+		}
+
+		let mut statuses = self.statuses.clone();
+
+		if let Some(statuses) = statuses.as_mut() {
+			statuses.sort_unstable_by_key(|status| i16::from(*status));
+			statuses.dedup();
+		}
+
+		Self {
+			search: self
+				.search
+				.as_ref()
+				.map(|search| search.to_ascii_lowercase()),
+			definition_query: self
+				.definition_query
+				.as_ref()
+				.map(|query| query.trim().to_string()),
+			pattern: self.pattern.clone(),
+			tags: self.tags.clone().and_then(normalize),
+			length: self.length,
+			frequency: self.frequency,
+			popularity: self.popularity,
+			statuses,
+			include_tags: self.include_tags.clone().and_then(normalize),
+			exclude_tags: self.exclude_tags.clone().and_then(normalize),
+			from: self.from,
+			to: self.to,
+		}
+	}
+
+	/// Builds a query for every name matching the filter, joined with the likes of `user_id`.
+	pub fn query(&self, user_id: i32) -> BoxedNames<'_> {
+		let mut names = schema::name::table
+			.left_join(
+				schema::like::table.on(schema::like::username
+					.eq(schema::name::username)
+					.and(schema::like::user_id.eq(user_id))),
+			)
+			.into_boxed();
+
+		if let Some(search) = &self.search {
+			names = names
+				.filter(schema::name::username.like(format!("%{}%", search.to_ascii_lowercase())));
+		}
+
+		if let Some(pattern) = &self.pattern {
+			names = names.filter(schema::name::username.regex_match(pattern.as_regex()));
+		}
+
+		if let Some(query) = &self.definition_query {
+			names = names
+				.filter(schema::name::definition_search.matches(definition_query(query.as_str())));
+		}
+
+		if self
+			.tags
+			.as_ref()
+			.is_some_and(|tags| tags.contains(&"new".to_string()))
+		{
+			// filter for updated_at to be within the last 24 hours
+			names = names.filter(schema::name::updated_at.ge(chrono::Utc::now().naive_utc()
+				- chrono::Duration::try_days(1).expect("1 day to be less than i64::MAX / 1_000")));
+		} else if let Some(from) = self.from {
+			names = names.filter(schema::name::updated_at.ge(from));
+		}
+
+		if let Some(to) = self.to {
+			names = names.filter(schema::name::updated_at.le(to));
+		}
+
+		macro_rules! filter_range {
+			($column:expr, $range:expr) => {
+				if let Some(range) = $range {
+					if let Some(min) = range.min {
+						names = names.filter($column.ge(min));
+					}
+
+					if let Some(max) = range.max {
+						names = names.filter($column.le(max));
+					}
+				}
+			};
+		}
+
+		filter_range!(schema::name::length, self.length);
+		filter_range!(schema::name::frequency, self.frequency);
+		filter_range!(schema::name::popularity, self.popularity);
+
+		let mut other_tags = self
+			.include_tags
+			.iter()
+			.flatten()
+			.cloned()
+			.collect::<Vec<_>>();
+		let mut has_taken_tag = false;
+
+		if let Some(tags) = self.tags.as_ref() {
+			for tag in tags {
+				match Tag::from(tag.as_str()) {
+					Tag::Common => {
+						names = names.filter(schema::name::frequency.ge(COMMON_FREQUENCY))
+					}
+					Tag::Short => names = names.filter(schema::name::length.le(SHORT_LENGTH)),
+					Tag::Letters => {
+						names = names.filter(schema::name::username.regex_match("^[a-z]+$"));
+					}
+					Tag::Digits => {
+						names = names.filter(schema::name::username.regex_match("[0-9]"));
+					}
+					Tag::Underscore => names = names.filter(schema::name::username.like("%\\_%")),
+					Tag::Liked => {
+						names = names.filter(schema::like::username.is_not_null());
+					}
+					Tag::Taken => {
+						names = names
+							.filter(schema::name::status.ne(i16::from(Status::BatchAvailable)));
+						has_taken_tag = true;
+					}
+					Tag::Banned => {
+						names = names.filter(schema::name::status.eq(i16::from(Status::Banned)));
+						has_taken_tag = true;
+					}
+					Tag::Other(tag) => other_tags.push(tag.to_string()),
+				}
+			}
+		}
+
+		if let Some(statuses) = &self.statuses {
+			names = names.filter(
+				schema::name::status.eq_any(statuses.iter().map(|status| i16::from(*status))),
+			);
+		} else if !has_taken_tag {
+			names = names.filter(schema::name::status.eq(i16::from(Status::Available)));
+		}
+
+		if !other_tags.is_empty() {
+			names = names.filter(schema::name::tags.contains(other_tags));
+		}
+
+		if let Some(exclude_tags) = self.exclude_tags.as_ref().filter(|tags| !tags.is_empty()) {
+			names = names.filter(diesel::dsl::not(
+				schema::name::tags.overlaps_with(exclude_tags.clone()),
+			));
+		}
+
+		names
+	}
+
+	/// Returns `true` if `name` is one of the names selected by [`NameFilter::query`], for
+	/// a user who `liked` the name. `None` skips the `liked` tag, for callers that only look
+	/// likes up for names matching everything else.
+	///
+	/// `pattern` and `definitionQuery` are not checked, since they can only be matched by the database.
+	pub fn matches(&self, name: &Name, liked: Option<bool>) -> bool {
+		if let Some(search) = &self.search {
+			if !name.username.contains(&search.to_ascii_lowercase()) {
+				return false;
+			}
+		}
+
+		let updated_at = name.updated_at.naive_utc();
+
+		if self
+			.tags
+			.as_ref()
+			.is_some_and(|tags| tags.contains(&"new".to_string()))
+		{
+			if updated_at
+				< chrono::Utc::now().naive_utc()
+					- chrono::Duration::try_days(1).expect("1 day to be less than i64::MAX / 1_000")
+			{
+				return false;
+			}
+		} else if self.from.is_some_and(|from| updated_at < from) {
+			return false;
+		}
+
+		if self.to.is_some_and(|to| updated_at > to) {
+			return false;
+		}
+
+		if !(self.length.is_none_or(|range| range.contains(name.length))
+			&& self
+				.frequency
+				.is_none_or(|range| range.contains(name.frequency))
+			&& self
+				.popularity
+				.is_none_or(|range| range.contains(name.popularity)))
+		{
+			return false;
+		}
+
+		let mut has_taken_tag = false;
+
+		for tag in self.tags.iter().flatten() {
+			let matches = match Tag::from(tag.as_str()) {
+				Tag::Common => name.frequency >= COMMON_FREQUENCY,
+				Tag::Short => name.length <= SHORT_LENGTH,
+				Tag::Letters => {
+					!name.username.is_empty()
+						&& name.username.bytes().all(|c| c.is_ascii_lowercase())
+				}
+				Tag::Digits => name.username.bytes().any(|c| c.is_ascii_digit()),
+				Tag::Underscore => name.username.contains('_'),
+				Tag::Liked => liked.unwrap_or(true),
+				Tag::Taken => {
+					has_taken_tag = true;
+					name.status != i16::from(Status::BatchAvailable)
+				}
+				Tag::Banned => {
+					has_taken_tag = true;
+					name.status == i16::from(Status::Banned)
+				}
+				Tag::Other(tag) => name.tags.iter().any(|t| t == tag),
+			};
+
+			if !matches {
+				return false;
+			}
+		}
+
+		let has_status = match &self.statuses {
+			Some(statuses) => statuses
+				.iter()
+				.any(|status| i16::from(*status) == name.status),
+			None => has_taken_tag || name.status == i16::from(Status::Available),
+		};
+
+		has_status
+			&& self
+				.include_tags
+				.iter()
+				.flatten()
+				.all(|tag| name.tags.contains(tag))
+			&& !self
+				.exclude_tags
+				.iter()
+				.flatten()
+				.any(|tag| name.tags.contains(tag))
+	}
+}
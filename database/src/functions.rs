@@ -1,6 +1,50 @@
 use diesel::{
-	sql_function,
-	sql_types::{Text, Timestamptz},
+	define_sql_function,
+	expression::{AsExpression, Expression},
+	infix_operator,
+	query_builder::QueryId,
+	sql_types::{Nullable, SqlType, Text, Timestamptz},
 };
 
-sql_function!(fn date_trunc(field: Text, timestamp: Timestamptz) -> Timestamptz);
+/// The Postgres `tsvector` type, a document prepared for full-text search.
+#[derive(SqlType, QueryId)]
+#[diesel(postgres_type(name = "tsvector"))]
+pub struct TsVector;
+
+/// The Postgres `tsquery` type, a parsed full-text search query.
+#[derive(SqlType, QueryId)]
+#[diesel(postgres_type(name = "tsquery"))]
+pub struct TsQuery;
+
+define_sql_function!(fn date_trunc(field: Text, timestamp: Timestamptz) -> Timestamptz);
+define_sql_function!(fn coalesce(a: Nullable<Text>, b: Nullable<Text>) -> Nullable<Text>);
+define_sql_function! {
+	/// Parses a search over definitions, written the way it would be typed into a search box.
+	fn definition_query(query: Text) -> TsQuery;
+}
+define_sql_function! {
+	/// How well the definitions match a query. Without a query this is `NULL`,
+	/// so it should be selected with `.nullable()` when the query is optional.
+	fn definition_rank(vector: TsVector, query: Nullable<Text>) -> Double;
+}
+
+infix_operator!(RegexMatch, " ~ ", backend: diesel::pg::Pg);
+infix_operator!(TextSearchMatch, " @@ ", backend: diesel::pg::Pg);
+
+pub trait RegexExpressionMethods: Expression<SqlType = Text> + Sized {
+	/// Creates a `self ~ pattern` expression, which matches a POSIX regular expression.
+	fn regex_match<T: AsExpression<Text>>(self, pattern: T) -> RegexMatch<Self, T::Expression> {
+		RegexMatch::new(self, pattern.as_expression())
+	}
+}
+
+impl<T: Expression<SqlType = Text>> RegexExpressionMethods for T {}
+
+pub trait TextSearchExpressionMethods: Expression<SqlType = TsVector> + Sized {
+	/// Creates a `self @@ query` expression, which matches documents containing the query.
+	fn matches<T: Expression<SqlType = TsQuery>>(self, query: T) -> TextSearchMatch<Self, T> {
+		TextSearchMatch::new(self, query)
+	}
+}
+
+impl<T: Expression<SqlType = TsVector>> TextSearchExpressionMethods for T {}
@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use diesel::{
+	BoolExpressionMethods, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+	QueryResult, RunQueryDsl,
+};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::{
+	models::{ApiKey, NewApiKey},
+	schema,
+};
+
+/// The number of leading characters of a key that are stored in plaintext to look it up
+pub const PREFIX_LENGTH: usize = 8;
+
+/// Returns the part of `key` that is stored in plaintext.
+pub fn prefix(key: &str) -> String {
+	key.chars().take(PREFIX_LENGTH).collect()
+}
+
+/// Returns the hex-encoded SHA-256 hash of `key`.
+pub fn hash(key: &str) -> String {
+	hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Compares `key` against a stored hash in constant time.
+pub fn verify(key: &str, hash: &str) -> bool {
+	self::hash(key).as_bytes().ct_eq(hash.as_bytes()).into()
+}
+
+/// Generates a new random key.
+pub fn generate() -> String {
+	let mut bytes = [0; 32];
+	rand::thread_rng().fill_bytes(&mut bytes);
+
+	hex::encode(bytes)
+}
+
+/// Returns the key matching `key`, if it exists and has not expired or been revoked.
+pub fn authenticate(connection: &mut PgConnection, key: &str) -> QueryResult<Option<ApiKey>> {
+	let candidates = schema::api_key::table
+		.filter(schema::api_key::prefix.eq(prefix(key)))
+		.filter(schema::api_key::revoked_at.is_null())
+		.filter(
+			schema::api_key::expires_at
+				.is_null()
+				.or(schema::api_key::expires_at.gt(diesel::dsl::now)),
+		)
+		.load::<ApiKey>(connection)?;
+
+	let Some(api_key) = candidates
+		.into_iter()
+		.find(|candidate| verify(key, &candidate.hash))
+	else {
+		return Ok(None);
+	};
+
+	// only write the last use at most once a minute to avoid a write on every request
+	diesel::update(schema::api_key::table)
+		.filter(schema::api_key::id.eq(api_key.id))
+		.filter(
+			schema::api_key::last_used_at
+				.is_null()
+				.or(schema::api_key::last_used_at.lt(Utc::now()
+					- chrono::Duration::try_minutes(1)
+						.expect("1 minute to be less than i64::MAX / 1_000"))),
+		)
+		.set(schema::api_key::last_used_at.eq(diesel::dsl::now))
+		.execute(connection)?;
+
+	Ok(Some(api_key))
+}
+
+/// Issues a new key for a user, returning it alongside the plaintext key.
+///
+/// The plaintext key is not stored, so it cannot be retrieved again.
+pub fn issue(
+	connection: &mut PgConnection,
+	user_id: i32,
+	label: &str,
+	expires_at: Option<DateTime<Utc>>,
+) -> QueryResult<(ApiKey, String)> {
+	let key = generate();
+
+	let api_key = diesel::insert_into(schema::api_key::table)
+		.values(NewApiKey {
+			user_id,
+			label,
+			prefix: &prefix(&key),
+			hash: &hash(&key),
+			expires_at,
+		})
+		.get_result::<ApiKey>(connection)?;
+
+	Ok((api_key, key))
+}
+
+/// Revokes a key. If `user_id` is provided, the key must belong to that user.
+///
+/// Returns `false` if no active key was found.
+pub fn revoke(connection: &mut PgConnection, id: i32, user_id: Option<i32>) -> QueryResult<bool> {
+	let mut query = diesel::update(schema::api_key::table)
+		.filter(schema::api_key::id.eq(id))
+		.filter(schema::api_key::revoked_at.is_null())
+		.into_boxed();
+
+	if let Some(user_id) = user_id {
+		query = query.filter(schema::api_key::user_id.eq(user_id));
+	}
+
+	query
+		.set(schema::api_key::revoked_at.eq(diesel::dsl::now))
+		.execute(connection)
+		.map(|updates| updates > 0)
+}
+
+/// Revokes a key and issues a replacement with the same label and expiry.
+/// If `user_id` is provided, the key must belong to that user.
+///
+/// Returns `None` if no active key was found.
+pub fn rotate(
+	connection: &mut PgConnection,
+	id: i32,
+	user_id: Option<i32>,
+) -> QueryResult<Option<(ApiKey, String)>> {
+	diesel::Connection::transaction(connection, |connection| {
+		let mut query = diesel::update(schema::api_key::table)
+			.filter(schema::api_key::id.eq(id))
+			.filter(schema::api_key::revoked_at.is_null())
+			.into_boxed();
+
+		if let Some(user_id) = user_id {
+			query = query.filter(schema::api_key::user_id.eq(user_id));
+		}
+
+		let Some(previous) = query
+			.set(schema::api_key::revoked_at.eq(diesel::dsl::now))
+			.get_result::<ApiKey>(connection)
+			.optional()?
+		else {
+			return Ok(None);
+		};
+
+		issue(
+			connection,
+			previous.user_id,
+			&previous.label,
+			previous.expires_at,
+		)
+		.map(Some)
+	})
+}
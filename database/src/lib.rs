@@ -1,26 +1,92 @@
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
 use diesel::{
-	r2d2::{ConnectionManager, Pool},
-	PgConnection,
+	r2d2::{self, ConnectionManager, CustomizeConnection, Pool},
+	Connection, PgConnection, RunQueryDsl,
 };
 
+pub mod alerts;
+pub mod channels;
+pub mod crypto;
+pub mod explain;
+pub mod filter;
 pub mod functions;
+pub mod keys;
+pub mod migrations;
 pub mod models;
+pub mod popularity;
+pub mod proxies;
 pub mod schema;
+pub mod tiers;
+pub mod webhooks;
 
-pub fn get_pool() -> PostgresPool {
+fn manager() -> ConnectionManager<PgConnection> {
 	let url = std::env::var("DATABASE_URL").expect("environment variable DATABASE_URL not found");
-	let manager = ConnectionManager::<PgConnection>::new(url);
 
+	ConnectionManager::<PgConnection>::new(url)
+}
+
+pub fn get_pool() -> PostgresPool {
+	Pool::builder()
+		.build(manager())
+		.expect("failed to create connection pool")
+}
+
+/// Cancels any statement on the connection that runs for longer than the timeout.
+#[derive(Debug)]
+struct StatementTimeout(Duration);
+
+impl CustomizeConnection<PgConnection, r2d2::Error> for StatementTimeout {
+	fn on_acquire(&self, connection: &mut PgConnection) -> Result<(), r2d2::Error> {
+		diesel::sql_query(format!("SET statement_timeout = {}", self.0.as_millis()))
+			.execute(connection)
+			.map(|_| ())
+			.map_err(r2d2::Error::QueryError)
+	}
+}
+
+/// Creates a pool of at most `size` connections, where statements are cancelled after
+/// `statement_timeout` and waiting for a connection gives up after `connection_timeout`.
+pub fn get_bounded_pool(
+	size: u32,
+	statement_timeout: Duration,
+	connection_timeout: Duration,
+) -> PostgresPool {
 	Pool::builder()
-		.build(manager)
+		.max_size(size)
+		.connection_timeout(connection_timeout)
+		.connection_customizer(Box::new(StatementTimeout(statement_timeout)))
+		.build(manager())
 		.expect("failed to create connection pool")
 }
 
+/// Runs `f` in a transaction without the statement timeout set by [`get_bounded_pool`],
+/// for background work that is expected to take longer than a request.
+pub fn without_statement_timeout<T>(
+	connection: &mut PgConnection,
+	f: impl FnOnce(&mut PgConnection) -> diesel::QueryResult<T>,
+) -> diesel::QueryResult<T> {
+	connection.transaction(|connection| {
+		diesel::sql_query("SET LOCAL statement_timeout = 0").execute(connection)?;
+
+		f(connection)
+	})
+}
+
+/// Returns `true` if the error was caused by a statement exceeding its `statement_timeout`.
+pub fn is_statement_timeout(error: &diesel::result::Error) -> bool {
+	// the error kind is not exposed, so the message postgres sends is checked instead
+	matches!(
+		error,
+		diesel::result::Error::DatabaseError(_, info)
+			if info.message() == "canceling statement due to statement timeout"
+	)
+}
+
 pub type PostgresPool = Pool<ConnectionManager<PgConnection>>;
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum Status {
 	Unknown,
 	Available,
@@ -30,6 +96,28 @@ pub enum Status {
 	BatchTaken,
 }
 
+/// The process that observed a change in a name's status.
+#[derive(PartialEq, Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Source {
+	Batcher,
+	Checker,
+	Snipe,
+}
+
+impl Status {
+	fn is_taken(self) -> bool {
+		matches!(self, Status::Taken | Status::BatchTaken)
+	}
+
+	/// Returns `true` if moving from this status to `next` is a real change in
+	/// availability. A direct check and a batch check agreeing that a name is
+	/// taken is not a transition.
+	pub fn is_transition(self, next: Status) -> bool {
+		self != next && !(self.is_taken() && next.is_taken())
+	}
+}
+
 impl From<i16> for Status {
 	fn from(status: i16) -> Self {
 		match status {
@@ -69,3 +157,23 @@ impl FromStr for Status {
 		}
 	}
 }
+
+impl From<i16> for Source {
+	fn from(source: i16) -> Self {
+		match source {
+			1 => Source::Checker,
+			2 => Source::Snipe,
+			_ => Source::Batcher,
+		}
+	}
+}
+
+impl From<Source> for i16 {
+	fn from(source: Source) -> Self {
+		match source {
+			Source::Batcher => 0,
+			Source::Checker => 1,
+			Source::Snipe => 2,
+		}
+	}
+}
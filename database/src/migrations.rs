@@ -0,0 +1,17 @@
+use diesel::PgConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+/// Every migration in the `migrations` directory, embedded at compile time
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("../migrations");
+
+/// Returns `true` if every migration the binary was built with has been applied.
+///
+/// # Errors
+/// Returns an error if the applied migrations could not be read.
+pub fn is_current(
+	connection: &mut PgConnection,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+	connection
+		.has_pending_migration(MIGRATIONS)
+		.map(|pending| !pending)
+}
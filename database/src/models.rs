@@ -1,28 +1,95 @@
 use chrono::{DateTime, Utc};
-use diesel::prelude::Queryable;
+use diesel::prelude::*;
+use serde::Serialize;
 
-#[derive(Queryable)]
+use crate::{schema, Source, Status};
+
+/// A name, without `definition_search` since it is derived from `definition` and only
+/// used to filter names.
+#[derive(Queryable, Selectable, Serialize, Clone)]
+#[diesel(table_name = schema::name, check_for_backend(diesel::pg::Pg))]
+#[serde(rename_all = "camelCase")]
 pub struct Name {
 	pub username: String,
-	pub created_at: DateTime<Utc>,
-	pub updated_at: DateTime<Utc>,
-	pub checked_at: DateTime<Utc>,
-	pub verified_at: DateTime<Utc>,
-	pub definition: Option<Vec<String>>,
-	pub frequency: f32,
+	pub popularity: f64,
+	pub definition: Vec<String>,
+	pub frequency: f64,
 	pub length: i32,
 	pub updating: bool,
-	pub tags: Option<Vec<String>>,
+	pub tags: Vec<String>,
 	pub status: i16,
+	pub verified_at: DateTime<Utc>,
+	pub checked_at: DateTime<Utc>,
+	pub updated_at: DateTime<Utc>,
+	pub created_at: DateTime<Utc>,
 }
 
-#[derive(Queryable)]
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::name_event, check_for_backend(diesel::pg::Pg))]
+pub struct NameEvent {
+	pub id: i64,
+	pub username: String,
+	pub old_status: i16,
+	pub new_status: i16,
+	pub source: i16,
+	pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = schema::name_event)]
+pub struct NewNameEvent<'a> {
+	pub username: &'a str,
+	pub old_status: i16,
+	pub new_status: i16,
+	pub source: i16,
+}
+
+impl<'a> NewNameEvent<'a> {
+	pub fn new(username: &'a str, old_status: Status, new_status: Status, source: Source) -> Self {
+		Self {
+			username,
+			old_status: old_status.into(),
+			new_status: new_status.into(),
+			source: source.into(),
+		}
+	}
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::user, check_for_backend(diesel::pg::Pg))]
 pub struct User {
 	pub id: i32,
-	pub key: String,
+	pub admin: bool,
 }
 
-#[derive(Queryable)]
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::api_key, check_for_backend(diesel::pg::Pg))]
+pub struct ApiKey {
+	pub id: i32,
+	pub user_id: i32,
+	pub label: String,
+	pub prefix: String,
+	pub hash: String,
+	pub created_at: DateTime<Utc>,
+	pub last_used_at: Option<DateTime<Utc>>,
+	pub expires_at: Option<DateTime<Utc>>,
+	pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = schema::api_key)]
+pub struct NewApiKey<'a> {
+	pub user_id: i32,
+	pub label: &'a str,
+	pub prefix: &'a str,
+	/// The hash of the key, since the key itself is never stored
+	pub hash: &'a str,
+	pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Queryable, Selectable, Identifiable, AsChangeset)]
+#[diesel(table_name = schema::proxy, check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
 pub struct Proxy {
 	pub id: i32,
 	pub address: String,
@@ -32,25 +99,175 @@ pub struct Proxy {
 	pub note: Option<String>,
 }
 
-#[derive(Queryable)]
+#[derive(Insertable)]
+#[diesel(table_name = schema::proxy)]
+pub struct NewProxy<'a> {
+	pub address: &'a str,
+	pub port: i32,
+	pub username: Option<&'a str>,
+	pub password: Option<&'a str>,
+	pub note: Option<&'a str>,
+}
+
+/// A checker account, where the username and password are encrypted with the server secret.
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::account, check_for_backend(diesel::pg::Pg))]
 pub struct Account {
 	pub id: i32,
 	pub username: String,
 	pub password: String,
 }
 
-#[derive(Queryable)]
+#[derive(Insertable)]
+#[diesel(table_name = schema::account)]
+pub struct NewAccount {
+	pub username: String,
+	pub password: String,
+}
+
+/// The fields of an account to change, where `None` leaves a field as it is.
+#[derive(AsChangeset)]
+#[diesel(table_name = schema::account)]
+pub struct AccountChanges {
+	pub username: Option<String>,
+	pub password: Option<String>,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::like, check_for_backend(diesel::pg::Pg))]
 pub struct Like {
 	pub username: String,
 	pub user_id: i32,
+	pub created_at: DateTime<Utc>,
+	pub note: Option<String>,
 }
 
-#[derive(Queryable)]
+/// A snipe, where the email and password are encrypted with the server secret.
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::snipe, check_for_backend(diesel::pg::Pg))]
 pub struct Snipe {
 	pub username: String,
-	pub created_at: chrono::DateTime<chrono::Utc>,
 	pub needed: i16,
 	pub count: i16,
 	pub email: String,
 	pub password: String,
+	pub created_at: DateTime<Utc>,
+	/// The user who created the snipe, if it was created through the API
+	pub user_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = schema::snipe)]
+pub struct NewSnipe {
+	pub username: String,
+	pub needed: i16,
+	pub count: i16,
+	pub email: String,
+	pub password: String,
+	pub user_id: Option<i32>,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::saved_search, check_for_backend(diesel::pg::Pg))]
+pub struct SavedSearch {
+	pub id: i32,
+	pub user_id: i32,
+	pub name: String,
+	pub filter: serde_json::Value,
+	pub alerts: bool,
+	pub created_at: DateTime<Utc>,
+	pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = schema::saved_search)]
+pub struct NewSavedSearch<'a> {
+	pub user_id: i32,
+	pub name: &'a str,
+	pub filter: serde_json::Value,
+	pub alerts: bool,
+}
+
+/// The fields of a saved search to change, where `None` leaves a field as it is.
+#[derive(AsChangeset)]
+#[diesel(table_name = schema::saved_search)]
+pub struct SavedSearchChanges<'a> {
+	pub name: Option<&'a str>,
+	pub filter: Option<serde_json::Value>,
+	pub alerts: Option<bool>,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::alert, check_for_backend(diesel::pg::Pg))]
+pub struct Alert {
+	pub id: i64,
+	pub user_id: i32,
+	pub saved_search_id: Option<i32>,
+	pub username: String,
+	pub name_event_id: i64,
+	pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = schema::alert)]
+pub struct NewAlert<'a> {
+	pub user_id: i32,
+	pub saved_search_id: Option<i32>,
+	pub username: &'a str,
+	pub name_event_id: i64,
+}
+
+/// A webhook, where the signing secret is encrypted with the server secret.
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::webhook, check_for_backend(diesel::pg::Pg))]
+pub struct Webhook {
+	pub id: i32,
+	pub user_id: i32,
+	pub url: String,
+	pub secret: String,
+	pub events: Vec<String>,
+	pub active: bool,
+	pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = schema::webhook)]
+pub struct NewWebhook<'a> {
+	pub user_id: i32,
+	pub url: &'a str,
+	pub secret: String,
+	pub events: Vec<&'a str>,
+}
+
+/// The fields of a webhook to change, where `None` leaves a field as it is.
+#[derive(AsChangeset)]
+#[diesel(table_name = schema::webhook)]
+pub struct WebhookChanges<'a> {
+	pub url: Option<&'a str>,
+	pub events: Option<Vec<&'a str>>,
+	pub active: Option<bool>,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = schema::webhook_delivery, check_for_backend(diesel::pg::Pg))]
+pub struct WebhookDelivery {
+	pub id: i64,
+	pub webhook_id: i32,
+	pub event: String,
+	pub payload: serde_json::Value,
+	pub status: i16,
+	pub attempts: i16,
+	pub response_status: Option<i32>,
+	pub error: Option<String>,
+	pub next_attempt_at: DateTime<Utc>,
+	pub created_at: DateTime<Utc>,
+	pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = schema::webhook_delivery)]
+pub struct NewWebhookDelivery<'a> {
+	pub webhook_id: i32,
+	pub event: &'a str,
+	pub payload: &'a serde_json::Value,
 }
@@ -0,0 +1,78 @@
+//! The popularity score of a name, which combines how often it is used, how many
+//! users like it and what kind of name it is.
+//!
+//! ```text
+//! popularity = FREQUENCY * ln(1 + frequency)
+//!            + LIKES * ln(1 + likes)
+//!            + LENGTH * max(0, SHORTEST_LONG_NAME - length) / (SHORTEST_LONG_NAME - 3)
+//!            + DEFINITION * (has a definition)
+//!            + TAG * tags
+//! ```
+
+use diesel::{
+	sql_types::{Double, Integer, Text},
+	ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+};
+
+use crate::schema;
+
+/// The weight of the logarithm of the frequency
+pub const FREQUENCY: f64 = 10.;
+/// The weight of the logarithm of the number of users that like the name
+pub const LIKES: f64 = 5.;
+/// The most the length of a name adds, given to three character names
+pub const LENGTH: f64 = 10.;
+/// Names with at least this many characters get nothing for their length
+pub const SHORTEST_LONG_NAME: i32 = 16;
+/// Added to names that are dictionary words
+pub const DEFINITION: f64 = 5.;
+/// Added for every tag on the name
+pub const TAG: f64 = 2.;
+
+/// Recomputes the popularity of every name, `batch_size` names at a time so that
+/// the table is never locked for long.
+///
+/// Returns the number of names whose popularity changed.
+pub fn update(connection: &mut PgConnection, batch_size: i64) -> QueryResult<usize> {
+	let mut after = String::new();
+	let mut updated = 0;
+
+	loop {
+		let batch = schema::name::table
+			.select(schema::name::username)
+			.filter(schema::name::username.gt(&after))
+			.order(schema::name::username.asc())
+			.limit(batch_size)
+			.load::<String>(connection)?;
+
+		let Some(last) = batch.into_iter().last() else {
+			return Ok(updated);
+		};
+
+		updated += diesel::sql_query(
+			"UPDATE name SET popularity = scores.popularity \
+			 FROM (SELECT name.username, \
+			 $3 * ln(1 + GREATEST(name.frequency, 0)) \
+			 + $4 * ln(1 + COUNT(\"like\".user_id)) \
+			 + $5 * GREATEST(0, $6 - name.length)::float8 / ($6 - 3) \
+			 + $7 * (cardinality(name.definition) > 0)::int \
+			 + $8 * cardinality(name.tags) AS popularity \
+			 FROM name LEFT JOIN \"like\" ON \"like\".username = name.username \
+			 WHERE name.username > $1 AND name.username <= $2 \
+			 GROUP BY name.username) AS scores \
+			 WHERE name.username = scores.username \
+			 AND name.popularity IS DISTINCT FROM scores.popularity",
+		)
+		.bind::<Text, _>(&after)
+		.bind::<Text, _>(&last)
+		.bind::<Double, _>(FREQUENCY)
+		.bind::<Double, _>(LIKES)
+		.bind::<Double, _>(LENGTH)
+		.bind::<Integer, _>(SHORTEST_LONG_NAME)
+		.bind::<Double, _>(DEFINITION)
+		.bind::<Double, _>(TAG)
+		.execute(connection)?;
+
+		after = last;
+	}
+}
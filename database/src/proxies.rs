@@ -0,0 +1,89 @@
+use crate::models::NewProxy;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("address must be a hostname or IPv4 address without a scheme, port or path")]
+	Address,
+	#[error("port must be between 1 and 65535")]
+	Port,
+	#[error("username and password must either both be provided or both be omitted")]
+	Credentials,
+	#[error("line must be in the format host:port or host:port:user:pass")]
+	Format,
+}
+
+impl Error {
+	/// The field of the proxy that is invalid.
+	pub fn field(&self) -> &'static str {
+		match self {
+			Error::Address => "address",
+			Error::Port => "port",
+			Error::Credentials => "password",
+			Error::Format => "line",
+		}
+	}
+}
+
+/// Returns the `host:port` string that checkers connect to the proxy with.
+pub fn url(address: &str, port: i32) -> String {
+	format!("{address}:{port}")
+}
+
+/// Validates a proxy the way checkers expect it to be stored.
+///
+/// # Errors
+/// - `Error::Address` if the address is empty or contains anything other than a host
+/// - `Error::Port` if the port is out of range
+/// - `Error::Credentials` if only one of the username and password is provided
+pub fn validate(
+	address: &str,
+	port: i32,
+	username: Option<&str>,
+	password: Option<&str>,
+) -> Result<(), Error> {
+	if address.is_empty()
+		|| !address
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+	{
+		return Err(Error::Address);
+	}
+
+	if !(1..=i32::from(u16::MAX)).contains(&port) {
+		return Err(Error::Port);
+	}
+
+	match (username, password) {
+		(Some(username), Some(password)) if !username.is_empty() && !password.is_empty() => Ok(()),
+		(None, None) => Ok(()),
+		_ => Err(Error::Credentials),
+	}
+}
+
+/// Parses and validates a proxy in the `host:port` or `host:port:user:pass` format.
+///
+/// # Errors
+/// - `Error::Format` if the line does not have two or four parts
+/// - `Error::Port` if the port is not a number in range
+/// - any error returned by [`validate`]
+pub fn parse(line: &str) -> Result<NewProxy<'_>, Error> {
+	let parts = line.trim().split(':').collect::<Vec<_>>();
+
+	let (address, port, username, password) = match parts.as_slice() {
+		[address, port] => (*address, *port, None, None),
+		[address, port, username, password] => (*address, *port, Some(*username), Some(*password)),
+		_ => return Err(Error::Format),
+	};
+
+	let port = port.parse().map_err(|_| Error::Port)?;
+
+	validate(address, port, username, password)?;
+
+	Ok(NewProxy {
+		address,
+		port,
+		username,
+		password,
+		note: None,
+	})
+}
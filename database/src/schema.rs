@@ -6,14 +6,44 @@ diesel::table! {
 	}
 }
 
+diesel::table! {
+	alert (id) {
+		id -> Int8,
+		user_id -> Int4,
+		saved_search_id -> Nullable<Int4>,
+		username -> Text,
+		name_event_id -> Int8,
+		created_at -> Timestamptz,
+	}
+}
+
+diesel::table! {
+	api_key (id) {
+		id -> Int4,
+		user_id -> Int4,
+		label -> Text,
+		prefix -> Text,
+		hash -> Text,
+		created_at -> Timestamptz,
+		last_used_at -> Nullable<Timestamptz>,
+		expires_at -> Nullable<Timestamptz>,
+		revoked_at -> Nullable<Timestamptz>,
+	}
+}
+
 diesel::table! {
 	like (username, user_id) {
 		username -> Text,
 		user_id -> Int4,
+		created_at -> Timestamptz,
+		note -> Nullable<Text>,
 	}
 }
 
 diesel::table! {
+	use diesel::sql_types::*;
+	use crate::functions::TsVector;
+
 	name (username) {
 		username -> Text,
 		popularity -> Float8,
@@ -27,6 +57,18 @@ diesel::table! {
 		checked_at -> Timestamptz,
 		updated_at -> Timestamptz,
 		created_at -> Timestamptz,
+		definition_search -> TsVector,
+	}
+}
+
+diesel::table! {
+	name_event (id) {
+		id -> Int8,
+		username -> Text,
+		old_status -> Int2,
+		new_status -> Int2,
+		source -> Int2,
+		created_at -> Timestamptz,
 	}
 }
 
@@ -41,6 +83,18 @@ diesel::table! {
 	}
 }
 
+diesel::table! {
+	saved_search (id) {
+		id -> Int4,
+		user_id -> Int4,
+		name -> Text,
+		filter -> Jsonb,
+		alerts -> Bool,
+		created_at -> Timestamptz,
+		updated_at -> Timestamptz,
+	}
+}
+
 diesel::table! {
 	snipe (username) {
 		username -> Text,
@@ -49,14 +103,76 @@ diesel::table! {
 		email -> Text,
 		password -> Text,
 		created_at -> Timestamptz,
+		user_id -> Nullable<Int4>,
+	}
+}
+
+diesel::table! {
+	snipe_worker (username, slot) {
+		username -> Text,
+		slot -> Int2,
 	}
 }
 
 diesel::table! {
 	user (id) {
 		id -> Int4,
-		key -> Text,
+		admin -> Bool,
+	}
+}
+
+diesel::joinable!(alert -> name (username));
+diesel::joinable!(alert -> name_event (name_event_id));
+diesel::joinable!(alert -> saved_search (saved_search_id));
+diesel::joinable!(alert -> user (user_id));
+diesel::joinable!(api_key -> user (user_id));
+diesel::joinable!(name_event -> name (username));
+diesel::joinable!(saved_search -> user (user_id));
+diesel::joinable!(snipe -> user (user_id));
+diesel::joinable!(snipe_worker -> snipe (username));
+diesel::joinable!(webhook -> user (user_id));
+diesel::joinable!(webhook_delivery -> webhook (webhook_id));
+
+diesel::table! {
+	webhook (id) {
+		id -> Int4,
+		user_id -> Int4,
+		url -> Text,
+		secret -> Text,
+		events -> Array<Text>,
+		active -> Bool,
+		created_at -> Timestamptz,
+	}
+}
+
+diesel::table! {
+	webhook_delivery (id) {
+		id -> Int8,
+		webhook_id -> Int4,
+		event -> Text,
+		payload -> Jsonb,
+		status -> Int2,
+		attempts -> Int2,
+		response_status -> Nullable<Int4>,
+		error -> Nullable<Text>,
+		next_attempt_at -> Timestamptz,
+		created_at -> Timestamptz,
+		delivered_at -> Nullable<Timestamptz>,
 	}
 }
 
-diesel::allow_tables_to_appear_in_same_query!(account, like, name, proxy, snipe, user,);
+diesel::allow_tables_to_appear_in_same_query!(
+	account,
+	alert,
+	api_key,
+	like,
+	name,
+	name_event,
+	proxy,
+	saved_search,
+	snipe,
+	snipe_worker,
+	user,
+	webhook,
+	webhook_delivery,
+);
@@ -0,0 +1,11 @@
+//! The frequency bounds of the priority tiers the checker works through.
+//!
+//! Names at or above [`HIGH`] are checked most often, followed by names at or
+//! above [`MEDIUM`], then names at or above [`LOW`] (or with a definition).
+
+/// The lowest frequency of a name in the high priority tier
+pub const HIGH: f64 = 15.;
+/// The lowest frequency of a name in the medium priority tier
+pub const MEDIUM: f64 = 0.01;
+/// The lowest frequency of a name in the low priority tier, unless it has a definition
+pub const LOW: f64 = 0.001;
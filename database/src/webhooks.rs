@@ -0,0 +1,218 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use diesel::{
+	ExpressionMethods, PgArrayExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{models::NewWebhookDelivery, schema};
+
+/// The number of attempts made to deliver a payload before it is marked as failed
+pub const MAX_ATTEMPTS: i16 = 8;
+
+/// The delay before the first retry, which doubles with every failed attempt
+const BASE_DELAY_SECONDS: i64 = 30;
+/// The longest delay between two attempts
+const MAX_DELAY_SECONDS: i64 = 6 * 60 * 60;
+
+/// Something that happened that webhooks can subscribe to.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+	NameAvailable,
+	NameTaken,
+	SnipeSucceeded,
+	SnipeFailed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+	Pending,
+	Succeeded,
+	Failed,
+}
+
+impl Event {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Event::NameAvailable => "name-available",
+			Event::NameTaken => "name-taken",
+			Event::SnipeSucceeded => "snipe-succeeded",
+			Event::SnipeFailed => "snipe-failed",
+		}
+	}
+}
+
+impl From<i16> for DeliveryStatus {
+	fn from(status: i16) -> Self {
+		match status {
+			1 => DeliveryStatus::Succeeded,
+			2 => DeliveryStatus::Failed,
+			_ => DeliveryStatus::Pending,
+		}
+	}
+}
+
+impl From<DeliveryStatus> for i16 {
+	fn from(status: DeliveryStatus) -> Self {
+		match status {
+			DeliveryStatus::Pending => 0,
+			DeliveryStatus::Succeeded => 1,
+			DeliveryStatus::Failed => 2,
+		}
+	}
+}
+
+/// Returns how long to wait before retrying a delivery that has failed `attempts` times.
+pub fn backoff(attempts: i16) -> chrono::Duration {
+	let exponent = u32::try_from(attempts.max(1) - 1).unwrap_or(0).min(16);
+	let seconds = BASE_DELAY_SECONDS
+		.saturating_mul(2_i64.saturating_pow(exponent))
+		.min(MAX_DELAY_SECONDS);
+
+	chrono::Duration::try_seconds(seconds).expect("delay to be less than i64::MAX / 1_000")
+}
+
+/// Returns `true` if `ip` is reachable from the internet, so that webhooks can never
+/// be used to send requests to services on the network the checker runs in.
+pub fn is_public(ip: IpAddr) -> bool {
+	match ip {
+		IpAddr::V4(ip) => {
+			let [first, second, ..] = ip.octets();
+			// 100.64.0.0/10 is shared by carrier-grade NAT
+			let is_shared = first == 100 && second & 0b1100_0000 == 64;
+
+			!(ip.is_unspecified()
+				|| ip.is_loopback()
+				|| ip.is_private()
+				|| ip.is_link_local()
+				|| ip.is_broadcast()
+				|| ip.is_multicast()
+				|| ip.is_documentation()
+				|| is_shared)
+		}
+		IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+			Some(ip) => is_public(IpAddr::V4(ip)),
+			None => {
+				!(ip.is_unspecified()
+					|| ip.is_loopback()
+					|| ip.is_unique_local()
+					|| ip.is_unicast_link_local()
+					|| ip.is_multicast())
+			}
+		},
+	}
+}
+
+/// Returns the value of the signature header for a payload sent at `timestamp`.
+///
+/// The signature is the hex-encoded HMAC-SHA256 of `{timestamp}.{body}`, so receivers
+/// can reject replayed payloads by checking the timestamp.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+	let mut mac =
+		Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC to accept keys of any size");
+
+	mac.update(timestamp.to_string().as_bytes());
+	mac.update(b".");
+	mac.update(body.as_bytes());
+
+	format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Queues a delivery of `data` to every active webhook subscribed to `event`.
+/// If `user_id` is provided, only the webhooks of that user receive it.
+///
+/// When called inside a transaction, nothing is delivered unless it commits.
+pub fn enqueue(
+	connection: &mut PgConnection,
+	event: Event,
+	user_id: Option<i32>,
+	data: &serde_json::Value,
+) -> QueryResult<usize> {
+	let mut webhooks = schema::webhook::table
+		.select(schema::webhook::id)
+		.filter(schema::webhook::active.eq(true))
+		.filter(schema::webhook::events.contains(vec![event.as_str()]))
+		.into_boxed();
+
+	if let Some(user_id) = user_id {
+		webhooks = webhooks.filter(schema::webhook::user_id.eq(user_id));
+	}
+
+	let webhooks = webhooks.load::<i32>(connection)?;
+
+	if webhooks.is_empty() {
+		return Ok(0);
+	}
+
+	let payload = serde_json::json!({
+		"event": event,
+		"createdAt": Utc::now(),
+		"data": data,
+	});
+
+	diesel::insert_into(schema::webhook_delivery::table)
+		.values(
+			webhooks
+				.into_iter()
+				.map(|webhook_id| NewWebhookDelivery {
+					webhook_id,
+					event: event.as_str(),
+					payload: &payload,
+				})
+				.collect::<Vec<_>>(),
+		)
+		.execute(connection)
+}
+
+/// Records the outcome of an attempt to deliver a payload, scheduling a retry if it failed
+/// and there are attempts remaining.
+pub fn record_attempt(
+	connection: &mut PgConnection,
+	id: i64,
+	attempts: i16,
+	response_status: Option<i32>,
+	error: Option<&str>,
+) -> QueryResult<()> {
+	let attempts = attempts + 1;
+	let now: DateTime<Utc> = Utc::now();
+
+	let status = match error {
+		None => DeliveryStatus::Succeeded,
+		Some(_) if attempts >= MAX_ATTEMPTS => DeliveryStatus::Failed,
+		Some(_) => DeliveryStatus::Pending,
+	};
+
+	diesel::update(schema::webhook_delivery::table)
+		.filter(schema::webhook_delivery::id.eq(id))
+		// the delivery may have been cancelled while it was being sent
+		.filter(schema::webhook_delivery::status.eq(i16::from(DeliveryStatus::Pending)))
+		.set((
+			schema::webhook_delivery::status.eq(i16::from(status)),
+			schema::webhook_delivery::attempts.eq(attempts),
+			schema::webhook_delivery::response_status.eq(response_status),
+			schema::webhook_delivery::error.eq(error),
+			schema::webhook_delivery::next_attempt_at.eq(now + backoff(attempts)),
+			schema::webhook_delivery::delivered_at
+				.eq((status == DeliveryStatus::Succeeded).then_some(now)),
+		))
+		.execute(connection)
+		.map(|_| ())
+}
+
+/// Marks every pending delivery of a webhook as failed, since deliveries are only
+/// sent to active webhooks and would otherwise stay pending forever.
+pub fn cancel_pending(connection: &mut PgConnection, webhook_id: i32) -> QueryResult<usize> {
+	diesel::update(schema::webhook_delivery::table)
+		.filter(schema::webhook_delivery::webhook_id.eq(webhook_id))
+		.filter(schema::webhook_delivery::status.eq(i16::from(DeliveryStatus::Pending)))
+		.set((
+			schema::webhook_delivery::status.eq(i16::from(DeliveryStatus::Failed)),
+			schema::webhook_delivery::error.eq("webhook was deactivated"),
+		))
+		.execute(connection)
+}
@@ -0,0 +1,39 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+	register_histogram, register_int_counter, register_int_counter_vec, Histogram, IntCounter,
+	IntCounterVec,
+};
+
+pub static BATCH_SIZE: Lazy<Histogram> = Lazy::new(|| {
+	register_histogram!(
+		"batcher_batch_size",
+		"Names in each batch",
+		vec![0., 10., 25., 50., 75., 100.]
+	)
+	.expect("metric to be registered once")
+});
+
+/// Names checked, where `result` is one of `available`, `unavailable` or `skipped`
+pub static NAMES: Lazy<IntCounterVec> = Lazy::new(|| {
+	register_int_counter_vec!(
+		"batcher_names_total",
+		"Names checked, by result",
+		&["result"]
+	)
+	.expect("metric to be registered once")
+});
+
+pub static PAUSES: Lazy<IntCounter> = Lazy::new(|| {
+	register_int_counter!(
+		"batcher_pauses_total",
+		"Times the batcher paused after too many names could not be checked"
+	)
+	.expect("metric to be registered once")
+});
+
+/// Registers every batcher metric so they are exported before they are first recorded.
+pub fn register() {
+	Lazy::force(&BATCH_SIZE);
+	Lazy::force(&NAMES);
+	Lazy::force(&PAUSES);
+}
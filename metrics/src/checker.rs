@@ -0,0 +1,57 @@
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter, register_int_counter_vec, IntCounter, IntCounterVec};
+
+/// Names checked, by the priority tier they were taken from and their resulting status
+pub static CHECKS: Lazy<IntCounterVec> = Lazy::new(|| {
+	register_int_counter_vec!(
+		"checker_checks_total",
+		"Names checked, by priority tier and resulting status",
+		&["tier", "status"]
+	)
+	.expect("metric to be registered once")
+});
+
+/// Names checked by each account, identified by its position in the account list
+pub static ACCOUNT_CHECKS: Lazy<IntCounterVec> = Lazy::new(|| {
+	register_int_counter_vec!(
+		"checker_account_checks_total",
+		"Names checked, by account",
+		&["account"]
+	)
+	.expect("metric to be registered once")
+});
+
+pub static RATE_LIMITS: Lazy<IntCounterVec> = Lazy::new(|| {
+	register_int_counter_vec!(
+		"checker_rate_limits_total",
+		"Checks that were rate limited, by account",
+		&["account"]
+	)
+	.expect("metric to be registered once")
+});
+
+pub static TOKEN_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+	register_int_counter_vec!(
+		"checker_token_failures_total",
+		"Failed attempts to get a Minecraft token, by account",
+		&["account"]
+	)
+	.expect("metric to be registered once")
+});
+
+pub static PROXY_REMOVALS: Lazy<IntCounter> = Lazy::new(|| {
+	register_int_counter!(
+		"checker_proxy_removals_total",
+		"Proxies removed from an account after being rejected"
+	)
+	.expect("metric to be registered once")
+});
+
+/// Registers every checker metric so they are exported before they are first recorded.
+pub fn register() {
+	Lazy::force(&CHECKS);
+	Lazy::force(&ACCOUNT_CHECKS);
+	Lazy::force(&RATE_LIMITS);
+	Lazy::force(&TOKEN_FAILURES);
+	Lazy::force(&PROXY_REMOVALS);
+}
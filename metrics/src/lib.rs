@@ -0,0 +1,94 @@
+//! Prometheus metrics for every binary, registered in the default registry.
+//!
+//! Each binary calls the `register` function of its module on startup so that
+//! its metrics are exported before they are first recorded.
+pub mod batcher;
+pub mod checker;
+pub mod server;
+
+use prometheus::{Encoder, TextEncoder};
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::{TcpListener, TcpStream},
+};
+
+/// The largest request accepted by [`serve`], which only needs the request line
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Returns the `Content-Type` of the output of [`gather`].
+pub fn content_type() -> String {
+	TextEncoder::new().format_type().to_string()
+}
+
+/// Encodes every registered metric in the Prometheus text format.
+pub fn gather() -> Vec<u8> {
+	let mut buffer = Vec::new();
+
+	TextEncoder::new()
+		.encode(&prometheus::gather(), &mut buffer)
+		.expect("metrics to always be encodable");
+
+	buffer
+}
+
+async fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+	let mut request = Vec::new();
+	let mut buffer = [0; 1024];
+
+	// only the request line is needed, but the headers are read so the client is not reset
+	while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+		let read = stream.read(&mut buffer).await?;
+
+		if read == 0 || request.len() + read > MAX_REQUEST_SIZE {
+			return Ok(());
+		}
+
+		request.extend_from_slice(&buffer[..read]);
+	}
+
+	let (status, content_type, body) = if request.starts_with(b"GET /metrics ") {
+		("200 OK", content_type(), gather())
+	} else {
+		("404 Not Found", "text/plain".to_string(), Vec::new())
+	};
+
+	stream
+		.write_all(
+			format!(
+				"HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+				body.len()
+			)
+			.as_bytes(),
+		)
+		.await?;
+	stream.write_all(&body).await?;
+	stream.shutdown().await
+}
+
+/// Serves the metrics at `GET /metrics` on `address` until the process exits.
+///
+/// # Errors
+/// Returns an error if `address` could not be bound.
+pub async fn serve(address: &str) -> std::io::Result<()> {
+	let listener = TcpListener::bind(address).await?;
+
+	loop {
+		let (stream, _) = listener.accept().await?;
+
+		tokio::spawn(async move {
+			respond(stream).await.ok();
+		});
+	}
+}
+
+/// Serves the metrics in the background on the address in the `variable`
+/// environment variable, or `default` if it is not set.
+pub fn spawn_from_env(variable: &str, default: &str) {
+	let address = std::env::var(variable).unwrap_or_else(|_| default.to_string());
+
+	tokio::spawn(async move {
+		if let Err(e) = serve(&address).await {
+			println!("Could not serve metrics on {address}: {e}");
+		}
+	});
+}
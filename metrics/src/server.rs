@@ -0,0 +1,42 @@
+use once_cell::sync::Lazy;
+use prometheus::{register_histogram_vec, register_int_gauge, HistogramVec, IntGauge};
+
+/// Request latency, where `route` is the matched pattern rather than the path
+/// so that names and ids do not create a series each
+pub static REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+	register_histogram_vec!(
+		"http_request_duration_seconds",
+		"Time taken to respond to a request, by method, route and status",
+		&["method", "route", "status"]
+	)
+	.expect("metric to be registered once")
+});
+
+pub static POOL_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+	register_int_gauge!("db_pool_connections", "Database connections currently open")
+		.expect("metric to be registered once")
+});
+
+pub static POOL_IDLE_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+	register_int_gauge!(
+		"db_pool_idle_connections",
+		"Database connections open but not in use"
+	)
+	.expect("metric to be registered once")
+});
+
+pub static POOL_MAX_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+	register_int_gauge!(
+		"db_pool_max_connections",
+		"The most database connections that can be open"
+	)
+	.expect("metric to be registered once")
+});
+
+/// Registers every server metric so they are exported before they are first recorded.
+pub fn register() {
+	Lazy::force(&REQUEST_DURATION);
+	Lazy::force(&POOL_CONNECTIONS);
+	Lazy::force(&POOL_IDLE_CONNECTIONS);
+	Lazy::force(&POOL_MAX_CONNECTIONS);
+}
@@ -0,0 +1,10 @@
+CREATE TABLE name_event (
+	id BIGSERIAL PRIMARY KEY,
+	username TEXT NOT NULL REFERENCES name (username) ON DELETE CASCADE,
+	old_status SMALLINT NOT NULL,
+	new_status SMALLINT NOT NULL,
+	source SMALLINT NOT NULL,
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX name_event_username_created_at_idx ON name_event (username, created_at);
@@ -0,0 +1,4 @@
+-- the plaintext keys cannot be recovered from their hashes, so every user needs a new key
+ALTER TABLE "user" ADD COLUMN key TEXT NOT NULL DEFAULT '';
+
+DROP TABLE api_key;
@@ -0,0 +1,20 @@
+CREATE TABLE api_key (
+	id SERIAL PRIMARY KEY,
+	user_id INTEGER NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+	label TEXT NOT NULL,
+	prefix TEXT NOT NULL,
+	hash TEXT NOT NULL,
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+	last_used_at TIMESTAMP WITH TIME ZONE,
+	expires_at TIMESTAMP WITH TIME ZONE,
+	revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX api_key_prefix_idx ON api_key (prefix);
+
+-- existing keys keep working, but are only stored as a hash from now on
+INSERT INTO api_key (user_id, label, prefix, hash)
+SELECT id, 'legacy', left(key, 8), encode(sha256(convert_to(key, 'UTF8')), 'hex')
+FROM "user";
+
+ALTER TABLE "user" DROP COLUMN key;
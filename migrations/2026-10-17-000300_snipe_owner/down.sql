@@ -0,0 +1 @@
+ALTER TABLE snipe DROP COLUMN user_id;
@@ -0,0 +1,4 @@
+-- snipes created before ownership was recorded have no owner
+ALTER TABLE snipe ADD COLUMN user_id INTEGER REFERENCES "user" (id) ON DELETE CASCADE;
+
+CREATE INDEX snipe_user_id_idx ON snipe (user_id);
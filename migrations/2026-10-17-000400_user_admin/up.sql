@@ -0,0 +1 @@
+ALTER TABLE "user" ADD COLUMN admin BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1 @@
+DROP INDEX name_username_trgm_idx;
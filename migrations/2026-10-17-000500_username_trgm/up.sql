@@ -0,0 +1,4 @@
+-- lets `LIKE` and regex searches on usernames use an index instead of scanning every name
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX name_username_trgm_idx ON name USING gin (username gin_trgm_ops);
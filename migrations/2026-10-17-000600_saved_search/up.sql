@@ -0,0 +1,25 @@
+CREATE TABLE saved_search (
+	id SERIAL PRIMARY KEY,
+	user_id INTEGER NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+	name TEXT NOT NULL,
+	filter JSONB NOT NULL,
+	-- whether names that become available and match the filter create an alert
+	alerts BOOLEAN NOT NULL DEFAULT true,
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+	updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX saved_search_user_id_idx ON saved_search (user_id);
+
+CREATE TABLE alert (
+	id BIGSERIAL PRIMARY KEY,
+	user_id INTEGER NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+	saved_search_id INTEGER REFERENCES saved_search (id) ON DELETE SET NULL,
+	username TEXT NOT NULL REFERENCES name (username) ON DELETE CASCADE,
+	-- the event of the name becoming available, so a user is alerted at most once per drop
+	name_event_id BIGINT NOT NULL REFERENCES name_event (id) ON DELETE CASCADE,
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+	UNIQUE (user_id, name_event_id)
+);
+
+CREATE INDEX alert_user_id_created_at_idx ON alert (user_id, created_at);
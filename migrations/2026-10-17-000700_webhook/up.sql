@@ -0,0 +1,31 @@
+CREATE TABLE webhook (
+	id SERIAL PRIMARY KEY,
+	user_id INTEGER NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+	url TEXT NOT NULL,
+	-- the key payloads are signed with, encrypted with the server secret
+	secret TEXT NOT NULL,
+	events TEXT[] NOT NULL,
+	active BOOLEAN NOT NULL DEFAULT true,
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX webhook_user_id_idx ON webhook (user_id);
+
+CREATE TABLE webhook_delivery (
+	id BIGSERIAL PRIMARY KEY,
+	webhook_id INTEGER NOT NULL REFERENCES webhook (id) ON DELETE CASCADE,
+	event TEXT NOT NULL,
+	payload JSONB NOT NULL,
+	status SMALLINT NOT NULL DEFAULT 0,
+	attempts SMALLINT NOT NULL DEFAULT 0,
+	-- the status code of the last attempt, if the endpoint responded
+	response_status INTEGER,
+	-- why the last attempt failed
+	error TEXT,
+	next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+	delivered_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX webhook_delivery_webhook_id_idx ON webhook_delivery (webhook_id, id);
+CREATE INDEX webhook_delivery_pending_idx ON webhook_delivery (next_attempt_at) WHERE status = 0;
@@ -0,0 +1,4 @@
+DROP INDEX like_user_id_created_at_idx;
+
+ALTER TABLE "like" DROP COLUMN note;
+ALTER TABLE "like" DROP COLUMN created_at;
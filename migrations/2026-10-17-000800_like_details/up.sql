@@ -0,0 +1,5 @@
+-- likes made before this have no record of when they were made
+ALTER TABLE "like" ADD COLUMN created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now();
+ALTER TABLE "like" ADD COLUMN note TEXT;
+
+CREATE INDEX like_user_id_created_at_idx ON "like" (user_id, created_at DESC);
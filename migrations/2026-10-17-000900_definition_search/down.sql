@@ -0,0 +1,7 @@
+DROP INDEX name_definition_search_idx;
+
+ALTER TABLE name DROP COLUMN definition_search;
+
+DROP FUNCTION definition_rank;
+DROP FUNCTION definition_query;
+DROP FUNCTION definition_vector;
@@ -0,0 +1,19 @@
+-- `array_to_string` is only stable, so generated columns and indexes need an immutable wrapper.
+-- Every definition of a name is searched as a single English document.
+CREATE FUNCTION definition_vector(definition TEXT[]) RETURNS tsvector
+	LANGUAGE sql IMMUTABLE STRICT PARALLEL SAFE
+	AS $$ SELECT to_tsvector('english', array_to_string(definition, ' ')) $$;
+
+-- parses a query the way a search box would, with quoted phrases, `or` and `-` to exclude words
+CREATE FUNCTION definition_query(query TEXT) RETURNS tsquery
+	LANGUAGE sql IMMUTABLE STRICT PARALLEL SAFE
+	AS $$ SELECT websearch_to_tsquery('english', query) $$;
+
+CREATE FUNCTION definition_rank(vector tsvector, query TEXT) RETURNS DOUBLE PRECISION
+	LANGUAGE sql IMMUTABLE STRICT PARALLEL SAFE
+	AS $$ SELECT ts_rank(vector, definition_query(query))::float8 $$;
+
+ALTER TABLE name ADD COLUMN definition_search tsvector NOT NULL
+	GENERATED ALWAYS AS (definition_vector(definition)) STORED;
+
+CREATE INDEX name_definition_search_idx ON name USING gin (definition_search);
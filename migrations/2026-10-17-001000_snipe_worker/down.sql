@@ -0,0 +1 @@
+DROP TABLE snipe_worker;
@@ -0,0 +1,8 @@
+-- each worker sniping a name holds one slot, so that a resized snipe knows
+-- exactly which workers to release instead of guessing from the count
+CREATE TABLE snipe_worker (
+	username TEXT NOT NULL REFERENCES snipe (username) ON DELETE CASCADE,
+	slot SMALLINT NOT NULL,
+	PRIMARY KEY (username, slot)
+);
+
@@ -0,0 +1,105 @@
+use actix_web::{dev::Payload, http::header, web, FromRequest, HttpRequest};
+use database::{keys, schema, PostgresPool};
+use diesel::{PgConnection, QueryDsl, RunQueryDsl};
+use futures::future::LocalBoxFuture;
+
+use crate::{error::Error, pool};
+
+/// A user authenticated with the API key in the `Authorization` header.
+///
+/// Extracting this rejects the request with a 401 if the key is missing, invalid,
+/// expired or revoked.
+pub struct User {
+	pub id: i32,
+	/// The id of the key used to authenticate the request
+	pub key_id: i32,
+}
+
+/// Returns the token in the `Authorization` header and the pool to check it with.
+fn token(req: &HttpRequest) -> Result<(String, web::Data<PostgresPool>), Error> {
+	// get the token from the "Authorization" header, or return a 401 if it does not exist or cannot be parsed
+	let token = req
+		.headers()
+		.get(header::AUTHORIZATION)
+		.ok_or(Error::Unauthorized)?
+		.to_str()
+		.map_err(|_| Error::Unauthorized)?
+		.to_string();
+
+	let pool = req
+		.app_data::<web::Data<PostgresPool>>()
+		.expect("pool to be registered as app data")
+		.clone();
+
+	Ok((token, pool))
+}
+
+impl User {
+	fn authenticate(connection: &mut PgConnection, token: &str) -> Result<Self, Error> {
+		let key = keys::authenticate(connection, token)?.ok_or(Error::Unauthorized)?;
+
+		Ok(Self {
+			id: key.user_id,
+			key_id: key.id,
+		})
+	}
+}
+
+impl FromRequest for User {
+	type Error = Error;
+	type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+	fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+		let token = token(req);
+
+		Box::pin(async move {
+			let (token, pool) = token?;
+
+			pool::run(&pool, move |connection| {
+				User::authenticate(connection, &token)
+			})
+			.await
+		})
+	}
+}
+
+/// A user that is allowed to manage checker accounts and proxies.
+///
+/// Extracting this rejects the request with a 401 if the user cannot be authenticated,
+/// or a 403 if they are not an admin.
+pub struct Admin;
+
+impl Admin {
+	fn authenticate(connection: &mut PgConnection, token: &str) -> Result<Self, Error> {
+		let user = User::authenticate(connection, token)?;
+
+		let admin = schema::user::table
+			.find(user.id)
+			.select(schema::user::admin)
+			.get_result::<bool>(connection)?;
+
+		if !admin {
+			return Err(Error::Forbidden);
+		}
+
+		Ok(Self)
+	}
+}
+
+impl FromRequest for Admin {
+	type Error = Error;
+	type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+	fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+		let token = token(req);
+
+		Box::pin(async move {
+			let (token, pool) = token?;
+
+			pool::run(&pool, move |connection| {
+				Admin::authenticate(connection, &token)
+			})
+			.await
+		})
+	}
+}
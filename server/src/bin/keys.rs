@@ -0,0 +1,128 @@
+//! Manages users and their API keys.
+//!
+//! ```text
+//! keys create-user
+//! keys set-admin <user id> <true|false>
+//! keys issue <user id> <label> [expires at (RFC 3339)]
+//! keys list <user id>
+//! keys rotate <key id>
+//! keys revoke <key id>
+//! ```
+use database::{keys, models::ApiKey, schema};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+const USAGE: &str = "usage:
+  keys create-user
+  keys set-admin <user id> <true|false>
+  keys issue <user id> <label> [expires at (RFC 3339)]
+  keys list <user id>
+  keys rotate <key id>
+  keys revoke <key id>";
+
+fn print_key(key: &ApiKey) {
+	println!(
+		"{}\t{}\t{}...\tcreated {}\tlast used {}\texpires {}\t{}",
+		key.id,
+		key.label,
+		key.prefix,
+		key.created_at,
+		key.last_used_at
+			.map_or_else(|| "never".to_string(), |at| at.to_string()),
+		key.expires_at
+			.map_or_else(|| "never".to_string(), |at| at.to_string()),
+		if key.revoked_at.is_some() {
+			"revoked"
+		} else {
+			"active"
+		}
+	);
+}
+
+fn parse_id(arg: Option<&String>) -> Result<i32, Box<dyn std::error::Error>> {
+	Ok(arg.ok_or(USAGE)?.parse()?)
+}
+
+fn main() {
+	dotenvy::dotenv().ok();
+
+	let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+	if let Err(e) = run(&args) {
+		eprintln!("{e}");
+		std::process::exit(1);
+	}
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+	let connection = &mut database::get_pool().get()?;
+
+	match args.first().map(String::as_str) {
+		Some("create-user") => {
+			let id = diesel::insert_into(schema::user::table)
+				.default_values()
+				.returning(schema::user::id)
+				.get_result::<i32>(connection)?;
+
+			println!("created user {id}");
+		}
+		Some("set-admin") => {
+			let user_id = parse_id(args.get(1))?;
+			let admin = args.get(2).ok_or(USAGE)?.parse::<bool>()?;
+
+			let updates = diesel::update(schema::user::table)
+				.filter(schema::user::id.eq(user_id))
+				.set(schema::user::admin.eq(admin))
+				.execute(connection)?;
+
+			if updates == 0 {
+				return Err("user not found".into());
+			}
+
+			println!("set admin to {admin} for user {user_id}");
+		}
+		Some("issue") => {
+			let user_id = parse_id(args.get(1))?;
+			let label = args.get(2).ok_or(USAGE)?;
+			let expires_at = args
+				.get(3)
+				.map(|at| chrono::DateTime::parse_from_rfc3339(at))
+				.transpose()?
+				.map(|at| at.to_utc());
+
+			let (api_key, key) = keys::issue(connection, user_id, label, expires_at)?;
+
+			print_key(&api_key);
+			println!("{key}");
+		}
+		Some("list") => {
+			let user_id = parse_id(args.get(1))?;
+			let keys = schema::api_key::table
+				.filter(schema::api_key::user_id.eq(user_id))
+				.order(schema::api_key::created_at.desc())
+				.load::<ApiKey>(connection)?;
+
+			for key in &keys {
+				print_key(key);
+			}
+		}
+		Some("rotate") => {
+			let id = parse_id(args.get(1))?;
+			let (api_key, key) = keys::rotate(connection, id, None)?.ok_or("key not found")?;
+
+			print_key(&api_key);
+			println!("{key}");
+		}
+		Some("revoke") => {
+			let id = parse_id(args.get(1))?;
+
+			if !keys::revoke(connection, id, None)? {
+				return Err("key not found".into());
+			}
+
+			println!("revoked key {id}");
+		}
+		_ => return Err(USAGE.into()),
+	}
+
+	Ok(())
+}
@@ -0,0 +1,75 @@
+//! Re-encrypts every stored credential with the current secret.
+//!
+//! Set `SECRET` to the new secret and `SECRET_PREVIOUS` to the old one, then run
+//! this once. Plaintext values written before encryption was introduced are
+//! encrypted as well. Afterwards, `SECRET_PREVIOUS` can be removed.
+use database::{
+	crypto::Secret,
+	models::{Account, AccountChanges, Snipe},
+	schema,
+};
+use diesel::{Connection, ExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper};
+
+fn main() {
+	dotenvy::dotenv().ok();
+
+	if let Err(e) = run() {
+		eprintln!("{e}");
+		std::process::exit(1);
+	}
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+	let secret = Secret::from_env();
+	let connection = &mut database::get_pool().get()?;
+
+	let (accounts, snipes, webhooks) = connection.transaction(|connection| {
+		let accounts = schema::account::table
+			.select(Account::as_select())
+			.for_update()
+			.load(connection)?;
+
+		for account in &accounts {
+			diesel::update(schema::account::table)
+				.filter(schema::account::id.eq(account.id))
+				.set(AccountChanges {
+					username: Some(secret.encrypt(&secret.decrypt(&account.username)?)),
+					password: Some(secret.encrypt(&secret.decrypt(&account.password)?)),
+				})
+				.execute(connection)?;
+		}
+
+		let snipes = schema::snipe::table
+			.select(Snipe::as_select())
+			.for_update()
+			.load(connection)?;
+
+		for snipe in &snipes {
+			diesel::update(schema::snipe::table)
+				.filter(schema::snipe::username.eq(&snipe.username))
+				.set((
+					schema::snipe::email.eq(secret.encrypt(&secret.decrypt(&snipe.email)?)),
+					schema::snipe::password.eq(secret.encrypt(&secret.decrypt(&snipe.password)?)),
+				))
+				.execute(connection)?;
+		}
+
+		let webhooks = schema::webhook::table
+			.select((schema::webhook::id, schema::webhook::secret))
+			.for_update()
+			.load::<(i32, String)>(connection)?;
+
+		for (id, signing_secret) in &webhooks {
+			diesel::update(schema::webhook::table)
+				.filter(schema::webhook::id.eq(id))
+				.set(schema::webhook::secret.eq(secret.encrypt(&secret.decrypt(signing_secret)?)))
+				.execute(connection)?;
+		}
+
+		Ok::<_, Box<dyn std::error::Error>>((accounts.len(), snipes.len(), webhooks.len()))
+	})?;
+
+	println!("re-encrypted {accounts} accounts, {snipes} snipes and {webhooks} webhooks");
+
+	Ok(())
+}
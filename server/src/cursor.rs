@@ -0,0 +1,328 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use database::{functions::definition_rank, schema};
+use diesel::{
+	pg::Pg, sql_types::Bool, BoolExpressionMethods, BoxableExpression, ExpressionMethods,
+	SelectableExpression,
+};
+use serde::{de::IgnoredAny, Deserialize, Deserializer, Serialize};
+
+use crate::handlers::names::FormattedName;
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Column {
+	Frequency,
+	Popularity,
+	Length,
+	UpdatedAt,
+	VerifiedAt,
+	Username,
+	/// How well the definitions match `definitionQuery`, which is required to sort by it
+	Rank,
+}
+
+/// The direction of a sort key, which defaults to descending for both `sort` and `orderBy`.
+#[derive(Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+	Asc,
+	#[default]
+	Desc,
+}
+
+/// A single key of a multi-key sort, as provided in `orderBy`.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SortKey {
+	pub column: Column,
+	#[serde(default)]
+	pub direction: Direction,
+}
+
+/// Deserializes a legacy `sort` or `column` option, treating values that are not
+/// recognised as missing like the listing always has.
+///
+/// # Errors
+/// Only fails if the input itself cannot be read.
+pub fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: Deserialize<'de>,
+{
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum Lenient<T> {
+		Known(T),
+		Unknown(IgnoredAny),
+	}
+
+	Ok(match Option::<Lenient<T>>::deserialize(deserializer)? {
+		Some(Lenient::Known(value)) => Some(value),
+		Some(Lenient::Unknown(_)) | None => None,
+	})
+}
+
+/// The value of a single sort key for the last row of a page.
+#[derive(Clone)]
+pub enum Value {
+	Float(f64),
+	Integer(i32),
+	Timestamp(chrono::DateTime<chrono::Utc>),
+	Text(String),
+}
+
+/// The sort keys used to order a listing of names. `username` is always
+/// the last key so that every row has a unique position in the ordering.
+pub struct Sort {
+	keys: Vec<(Column, Direction)>,
+	/// The search over definitions that the `rank` key ranks names by
+	query: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawCursor {
+	/// The signature of the sort the cursor was created with
+	s: String,
+	/// The values of each sort key, in the same order as the signature
+	v: Vec<serde_json::Value>,
+}
+
+impl Column {
+	fn name(self) -> &'static str {
+		match self {
+			Column::Frequency => "frequency",
+			Column::Popularity => "popularity",
+			Column::Length => "length",
+			Column::UpdatedAt => "updatedAt",
+			Column::VerifiedAt => "verifiedAt",
+			Column::Username => "username",
+			Column::Rank => "rank",
+		}
+	}
+
+	fn value(self, name: &FormattedName) -> Value {
+		match self {
+			Column::Frequency => Value::Float(name.name.frequency),
+			Column::Popularity => Value::Float(name.name.popularity),
+			Column::Length => Value::Integer(name.name.length),
+			Column::UpdatedAt => Value::Timestamp(name.name.updated_at),
+			Column::VerifiedAt => Value::Timestamp(name.name.verified_at),
+			Column::Username => Value::Text(name.name.username.clone()),
+			Column::Rank => Value::Float(name.rank.unwrap_or(0.)),
+		}
+	}
+
+	fn decode(self, value: serde_json::Value) -> Option<Value> {
+		Some(match self {
+			Column::Frequency | Column::Popularity | Column::Rank => {
+				Value::Float(serde_json::from_value(value).ok()?)
+			}
+			Column::Length => Value::Integer(serde_json::from_value(value).ok()?),
+			Column::UpdatedAt | Column::VerifiedAt => {
+				Value::Timestamp(serde_json::from_value(value).ok()?)
+			}
+			Column::Username => Value::Text(serde_json::from_value(value).ok()?),
+		})
+	}
+}
+
+impl Value {
+	fn encode(&self) -> serde_json::Value {
+		match self {
+			Value::Float(value) => serde_json::json!(value),
+			Value::Integer(value) => serde_json::json!(value),
+			Value::Timestamp(value) => serde_json::json!(value),
+			Value::Text(value) => serde_json::json!(value),
+		}
+	}
+}
+
+/// Builds a boxed comparison between a sort column and a cursor value.
+///
+/// `$op` is the name of the diesel comparison method (`gt`, `lt` or `eq`), and `$query`
+/// is the search over definitions that `rank` is computed for.
+macro_rules! compare {
+	($column:expr, $value:expr, $op:ident, $query:expr) => {
+		match ($column, $value) {
+			(Column::Frequency, Value::Float(value)) => {
+				Box::new(schema::name::frequency.$op(value)) as Predicate<'a, QS>
+			}
+			(Column::Popularity, Value::Float(value)) => {
+				Box::new(schema::name::popularity.$op(value))
+			}
+			(Column::Length, Value::Integer(value)) => Box::new(schema::name::length.$op(value)),
+			(Column::UpdatedAt, Value::Timestamp(value)) => {
+				Box::new(schema::name::updated_at.$op(value))
+			}
+			(Column::VerifiedAt, Value::Timestamp(value)) => {
+				Box::new(schema::name::verified_at.$op(value))
+			}
+			(Column::Username, Value::Text(value)) => Box::new(schema::name::username.$op(value)),
+			// sorting by rank is only allowed with a query, so it is never null
+			(Column::Rank, Value::Float(value)) => Box::new(
+				definition_rank(schema::name::definition_search, $query.clone()).$op(value),
+			),
+			_ => unreachable!("cursor values are decoded using their column"),
+		}
+	};
+}
+
+pub type Predicate<'a, QS> = Box<dyn BoxableExpression<QS, Pg, SqlType = Bool> + 'a>;
+
+impl Sort {
+	/// Creates the sort for the legacy `sort` and `column` options of a name listing,
+	/// which defaults to `frequency DESC`. `sort` only applies when `column` is
+	/// provided, so `asc` on its own still sorts by `frequency DESC`.
+	pub fn new(sort: Option<Direction>, column: Option<Column>) -> Self {
+		let direction = sort.unwrap_or_default();
+
+		match column {
+			None => Self::from_keys(&[SortKey {
+				column: Column::Frequency,
+				direction: Direction::Desc,
+			}]),
+			Some(Column::Frequency) => Self::from_keys(&[SortKey {
+				column: Column::Frequency,
+				direction,
+			}]),
+			Some(Column::Username) => Self::from_keys(&[SortKey {
+				column: Column::Username,
+				direction,
+			}]),
+			Some(column) => Self::from_keys(&[
+				SortKey { column, direction },
+				SortKey {
+					column: Column::Frequency,
+					direction: Direction::Desc,
+				},
+			]),
+		}
+	}
+
+	/// Creates a sort from the keys of an `orderBy` option. Keys are expected to be unique.
+	pub fn from_keys(keys: &[SortKey]) -> Self {
+		let mut keys = keys
+			.iter()
+			.map(|key| (key.column, key.direction))
+			.collect::<Vec<_>>();
+
+		if !keys.iter().any(|(column, _)| *column == Column::Username) {
+			keys.push((Column::Username, Direction::Asc));
+		}
+
+		Self { keys, query: None }
+	}
+
+	/// Sets the search over definitions that the `rank` key ranks names by.
+	pub fn ranked_by(mut self, query: Option<String>) -> Self {
+		self.query = query;
+		self
+	}
+
+	pub fn keys(&self) -> &[(Column, Direction)] {
+		&self.keys
+	}
+
+	pub fn query(&self) -> Option<&str> {
+		self.query.as_deref()
+	}
+
+	/// Describes the sort keys, so that two sorts have the same signature only if they are equal.
+	/// Sorts by `rank` also include the search, since the ranks depend on it.
+	pub fn signature(&self) -> String {
+		let keys = self
+			.keys
+			.iter()
+			.map(|(column, direction)| {
+				format!(
+					"{}:{}",
+					column.name(),
+					match direction {
+						Direction::Asc => "asc",
+						Direction::Desc => "desc",
+					}
+				)
+			})
+			.collect::<Vec<_>>()
+			.join(",");
+
+		match &self.query {
+			Some(query) if self.keys.iter().any(|(column, _)| *column == Column::Rank) => {
+				let mut hasher = DefaultHasher::new();
+				query.hash(&mut hasher);
+
+				format!("{keys};{:x}", hasher.finish())
+			}
+			_ => keys,
+		}
+	}
+
+	/// Returns the value of each sort key for `name`, for use with [`Sort::after`].
+	pub fn values(&self, name: &FormattedName) -> Vec<Value> {
+		self.keys
+			.iter()
+			.map(|(column, _)| column.value(name))
+			.collect()
+	}
+
+	/// Creates an opaque cursor pointing just after `name` in this ordering.
+	pub fn encode(&self, name: &FormattedName) -> String {
+		let cursor = RawCursor {
+			s: self.signature(),
+			v: self.values(name).iter().map(Value::encode).collect(),
+		};
+
+		URL_SAFE_NO_PAD
+			.encode(serde_json::to_vec(&cursor).expect("cursor to always be serializable"))
+	}
+
+	/// Decodes a cursor created by [`Sort::encode`], returning `None` if it is
+	/// malformed or was created with a different sort.
+	pub fn decode(&self, cursor: &str) -> Option<Vec<Value>> {
+		let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+		let cursor = serde_json::from_slice::<RawCursor>(&bytes).ok()?;
+
+		if cursor.s != self.signature() || cursor.v.len() != self.keys.len() {
+			return None;
+		}
+
+		self.keys
+			.iter()
+			.zip(cursor.v)
+			.map(|((column, _), value)| column.decode(value))
+			.collect()
+	}
+
+	/// Builds a predicate matching every row that comes after `values` in this ordering.
+	///
+	/// For keys `(a ASC, b DESC)`, this produces `a > $1 OR (a = $1 AND b < $2)`.
+	pub fn after<'a, QS: 'a>(&self, values: &[Value]) -> Predicate<'a, QS>
+	where
+		schema::name::frequency: SelectableExpression<QS>,
+		schema::name::popularity: SelectableExpression<QS>,
+		schema::name::length: SelectableExpression<QS>,
+		schema::name::updated_at: SelectableExpression<QS>,
+		schema::name::verified_at: SelectableExpression<QS>,
+		schema::name::username: SelectableExpression<QS>,
+		schema::name::definition_search: SelectableExpression<QS>,
+	{
+		let mut keys = self.keys.iter().zip(values.iter().cloned()).rev();
+		let past = |(column, direction): &(Column, Direction), value: Value| match direction {
+			Direction::Asc => compare!(*column, value, gt, self.query),
+			Direction::Desc => compare!(*column, value, lt, self.query),
+		};
+
+		let (key, value) = keys.next().expect("sort to have at least one key");
+		let mut predicate = past(key, value);
+
+		for (key, value) in keys {
+			let equal = compare!(key.0, value.clone(), eq, self.query);
+
+			predicate = Box::new(past(key, value).or(equal.and(predicate)));
+		}
+
+		predicate
+	}
+}
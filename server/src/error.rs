@@ -0,0 +1,148 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use database::filter;
+use serde::Serialize;
+
+/// An error returned from a handler, sent to the client as JSON.
+///
+/// Errors caused by the database are logged with their cause, which is not
+/// included in the response.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("a valid API key is required")]
+	Unauthorized,
+	#[error("an admin API key is required")]
+	Forbidden,
+	#[error("not found")]
+	NotFound,
+	#[error("{0}")]
+	BadRequest(String),
+	#[error("{0}")]
+	Conflict(&'static str),
+	/// A field that is well-formed but cannot be used
+	#[error(transparent)]
+	Invalid(#[from] filter::Error),
+	/// A body that is valid JSON but has missing or invalid fields
+	#[error("{message}")]
+	InvalidBody {
+		/// The field that is invalid, if it is known
+		field: Option<&'static str>,
+		message: String,
+	},
+	/// No database connection became available in time
+	#[error("the service is unavailable, try again later")]
+	Unavailable,
+	/// A query ran for longer than the statement timeout and was cancelled
+	#[error("the request took too long, try a narrower filter")]
+	Timeout,
+	#[error("an internal error occurred")]
+	Internal,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+	code: &'static str,
+	message: String,
+	/// The field that caused the error, if it was caused by a single field
+	#[serde(skip_serializing_if = "Option::is_none")]
+	field: Option<&'static str>,
+}
+
+impl Error {
+	/// A machine-readable code that identifies the kind of error.
+	pub fn code(&self) -> &'static str {
+		match self {
+			Self::Unauthorized => "unauthorized",
+			Self::Forbidden => "forbidden",
+			Self::NotFound => "not_found",
+			Self::BadRequest(..) => "bad_request",
+			Self::Conflict(..) => "conflict",
+			Self::Invalid(..) | Self::InvalidBody { .. } => "invalid",
+			Self::Unavailable => "unavailable",
+			Self::Timeout => "timeout",
+			Self::Internal => "internal",
+		}
+	}
+}
+
+/// Creates a 422 error describing why a field is invalid.
+pub fn invalid(field: &'static str, message: &'static str) -> Error {
+	Error::Invalid(filter::Error { field, message })
+}
+
+impl ResponseError for Error {
+	fn status_code(&self) -> StatusCode {
+		match self {
+			Self::Unauthorized => StatusCode::UNAUTHORIZED,
+			Self::Forbidden => StatusCode::FORBIDDEN,
+			Self::NotFound => StatusCode::NOT_FOUND,
+			Self::BadRequest(..) => StatusCode::BAD_REQUEST,
+			Self::Conflict(..) => StatusCode::CONFLICT,
+			Self::Invalid(..) | Self::InvalidBody { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+			Self::Unavailable | Self::Timeout => StatusCode::SERVICE_UNAVAILABLE,
+			Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+
+	fn error_response(&self) -> HttpResponse {
+		let field = match self {
+			Self::Invalid(error) => Some(error.field),
+			Self::InvalidBody { field, .. } => *field,
+			_ => None,
+		};
+
+		HttpResponse::build(self.status_code()).json(ErrorResponse {
+			code: self.code(),
+			message: self.to_string(),
+			field,
+		})
+	}
+}
+
+impl From<diesel::result::Error> for Error {
+	fn from(error: diesel::result::Error) -> Self {
+		if let diesel::result::Error::NotFound = error {
+			return Self::NotFound;
+		}
+
+		if database::is_statement_timeout(&error) {
+			return Self::Timeout;
+		}
+
+		eprintln!("database error: {error}");
+
+		Self::Internal
+	}
+}
+
+impl From<diesel::r2d2::PoolError> for Error {
+	fn from(error: diesel::r2d2::PoolError) -> Self {
+		eprintln!("could not get a database connection: {error}");
+
+		Self::Unavailable
+	}
+}
+
+impl From<actix_web::error::BlockingError> for Error {
+	fn from(error: actix_web::error::BlockingError) -> Self {
+		eprintln!("could not run a blocking task: {error}");
+
+		Self::Internal
+	}
+}
+
+impl From<database::proxies::Error> for Error {
+	fn from(error: database::proxies::Error) -> Self {
+		Self::InvalidBody {
+			field: Some(error.field()),
+			message: error.to_string(),
+		}
+	}
+}
+
+impl From<database::crypto::Error> for Error {
+	fn from(error: database::crypto::Error) -> Self {
+		eprintln!("could not decrypt a value: {error}");
+
+		Self::Internal
+	}
+}
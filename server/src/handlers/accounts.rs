@@ -0,0 +1,172 @@
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use database::{
+	crypto::Secret,
+	models::{Account, AccountChanges, NewAccount},
+	schema, PostgresPool,
+};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	auth::Admin,
+	error::{invalid, Error},
+	pool,
+};
+
+#[derive(Deserialize)]
+pub struct CreateAccountOptions {
+	pub username: String,
+	pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateAccountOptions {
+	pub username: Option<String>,
+	pub password: Option<String>,
+}
+
+/// A checker account without its password.
+#[derive(Serialize)]
+pub struct FormattedAccount {
+	pub id: i32,
+	pub username: String,
+}
+
+#[derive(Serialize)]
+pub struct ViewAccountsResponse {
+	pub data: Vec<FormattedAccount>,
+}
+
+#[derive(Serialize)]
+pub struct AccountResponse {
+	pub updated: bool,
+}
+
+fn format_account(secret: &Secret, account: Account) -> Result<FormattedAccount, Error> {
+	Ok(FormattedAccount {
+		id: account.id,
+		username: secret.decrypt(&account.username)?,
+	})
+}
+
+#[get("/admin/accounts")]
+pub async fn view_accounts(
+	_admin: Admin,
+	pool: web::Data<PostgresPool>,
+	secret: web::Data<Secret>,
+) -> Result<HttpResponse, Error> {
+	let accounts = pool::run(&pool, |connection| {
+		Ok(schema::account::table
+			.select(Account::as_select())
+			.order(schema::account::id.asc())
+			.load(connection)?)
+	})
+	.await?
+	.into_iter()
+	.map(|account| format_account(&secret, account))
+	.collect::<Result<_, _>>()?;
+
+	Ok(HttpResponse::Ok().json(ViewAccountsResponse { data: accounts }))
+}
+
+#[post("/admin/accounts")]
+pub async fn create_account(
+	data: web::Json<CreateAccountOptions>,
+	_admin: Admin,
+	pool: web::Data<PostgresPool>,
+	secret: web::Data<Secret>,
+) -> Result<HttpResponse, Error> {
+	if data.username.is_empty() {
+		return Err(invalid("username", "must not be empty"));
+	}
+
+	if data.password.is_empty() {
+		return Err(invalid("password", "must not be empty"));
+	}
+
+	let account = NewAccount {
+		username: secret.encrypt(&data.username),
+		password: secret.encrypt(&data.password),
+	};
+
+	let id = pool::run(&pool, move |connection| {
+		Ok(diesel::insert_into(schema::account::table)
+			.values(account)
+			.returning(schema::account::id)
+			.get_result::<i32>(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Created().json(FormattedAccount {
+		id,
+		username: data.into_inner().username,
+	}))
+}
+
+#[patch("/admin/accounts/{id}")]
+pub async fn update_account(
+	id: web::Path<i32>,
+	data: web::Json<UpdateAccountOptions>,
+	_admin: Admin,
+	pool: web::Data<PostgresPool>,
+	secret: web::Data<Secret>,
+) -> Result<HttpResponse, Error> {
+	if data.username.as_deref() == Some("") {
+		return Err(invalid("username", "must not be empty"));
+	}
+
+	if data.password.as_deref() == Some("") {
+		return Err(invalid("password", "must not be empty"));
+	}
+
+	let id = id.into_inner();
+	let changes = AccountChanges {
+		username: data
+			.username
+			.as_ref()
+			.map(|username| secret.encrypt(username)),
+		password: data
+			.password
+			.as_ref()
+			.map(|password| secret.encrypt(password)),
+	};
+
+	let account = pool::run(&pool, move |connection| {
+		diesel::update(schema::account::table)
+			.filter(schema::account::id.eq(id))
+			.set((
+				changes,
+				// keeps the update valid when neither field is provided
+				schema::account::id.eq(schema::account::id),
+			))
+			.returning(Account::as_returning())
+			.get_result(connection)
+			.optional()?
+			.ok_or(Error::NotFound)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(format_account(&secret, account)?))
+}
+
+#[delete("/admin/accounts/{id}")]
+pub async fn delete_account(
+	id: web::Path<i32>,
+	_admin: Admin,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let id = id.into_inner();
+
+	let updates = pool::run(&pool, move |connection| {
+		Ok(diesel::delete(schema::account::table)
+			.filter(schema::account::id.eq(id))
+			.execute(connection)?)
+	})
+	.await?;
+
+	if updates == 0 {
+		return Err(Error::NotFound);
+	}
+
+	Ok(HttpResponse::Ok().json(AccountResponse { updated: true }))
+}
@@ -0,0 +1,81 @@
+use actix_web::{get, web, HttpResponse};
+use database::{models::Alert, schema, PostgresPool};
+use diesel::{
+	ExpressionMethods, NullableExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{auth::User, error::Error, pool};
+
+/// The most alerts returned by a single request
+const MAX_LIMIT: i64 = 500;
+
+#[derive(Deserialize)]
+pub struct ViewAlertsOptions {
+	pub limit: Option<i64>,
+	/// Only return alerts with an id less than this, to fetch the next page
+	pub before: Option<i64>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedAlert {
+	pub id: i64,
+	#[serde(skip_serializing)]
+	pub user_id: i32,
+	/// The saved search that matched, or `None` if it has since been deleted
+	pub search_id: Option<i32>,
+	pub search_name: Option<String>,
+	pub username: String,
+	pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ViewAlertsResponse {
+	pub data: Vec<FormattedAlert>,
+}
+
+impl From<(Alert, Option<String>)> for FormattedAlert {
+	fn from((alert, search_name): (Alert, Option<String>)) -> Self {
+		Self {
+			id: alert.id,
+			user_id: alert.user_id,
+			search_id: alert.saved_search_id,
+			search_name,
+			username: alert.username,
+			created_at: alert.created_at,
+		}
+	}
+}
+
+#[get("/alerts")]
+pub async fn view_alerts(
+	options: web::Query<ViewAlertsOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let options = options.into_inner();
+
+	let alerts = pool::run(&pool, move |connection| {
+		let mut alerts = schema::alert::table
+			.left_join(schema::saved_search::table)
+			.select((Alert::as_select(), schema::saved_search::name.nullable()))
+			.filter(schema::alert::user_id.eq(user.id))
+			.order(schema::alert::id.desc())
+			.limit(options.limit.unwrap_or(50).clamp(0, MAX_LIMIT))
+			.into_boxed();
+
+		if let Some(before) = options.before {
+			alerts = alerts.filter(schema::alert::id.lt(before));
+		}
+
+		Ok(alerts
+			.load::<(Alert, Option<String>)>(connection)?
+			.into_iter()
+			.map(FormattedAlert::from)
+			.collect::<Vec<_>>())
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(ViewAlertsResponse { data: alerts }))
+}
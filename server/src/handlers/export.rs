@@ -0,0 +1,257 @@
+use actix_web::{http::header, post, web, HttpResponse};
+use database::{filter::liked, functions::definition_rank, models::Name, schema, PostgresPool};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+	auth::User,
+	cursor::{Sort, Value},
+	error::{invalid, Error},
+	handlers::names::{check_pattern, order_names, FormattedName, ViewNamesOptions},
+	pool,
+};
+
+/// The number of names loaded from the database at a time
+const BATCH_SIZE: i64 = 1_000;
+
+const CSV_HEADER: [&str; 10] = [
+	"username",
+	"status",
+	"frequency",
+	"popularity",
+	"length",
+	"liked",
+	"definition",
+	"tags",
+	"verifiedAt",
+	"updatedAt",
+];
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+	#[default]
+	Csv,
+	Ndjson,
+}
+
+#[derive(Deserialize)]
+pub struct ExportNamesQuery {
+	#[serde(default)]
+	pub format: Format,
+}
+
+impl Format {
+	fn content_type(self) -> &'static str {
+		match self {
+			Format::Csv => "text/csv; charset=utf-8",
+			Format::Ndjson => "application/x-ndjson",
+		}
+	}
+
+	fn extension(self) -> &'static str {
+		match self {
+			Format::Csv => "csv",
+			Format::Ndjson => "ndjson",
+		}
+	}
+
+	fn header(self) -> Option<Vec<u8>> {
+		match self {
+			Format::Csv => {
+				let mut writer = csv::Writer::from_writer(Vec::new());
+
+				writer
+					.write_record(CSV_HEADER)
+					.expect("writing to a vec to never fail");
+
+				Some(writer.into_inner().expect("writing to a vec to never fail"))
+			}
+			Format::Ndjson => None,
+		}
+	}
+
+	fn write(self, names: &[FormattedName]) -> Vec<u8> {
+		match self {
+			Format::Csv => {
+				let mut writer = csv::Writer::from_writer(Vec::new());
+
+				for FormattedName { name, liked, .. } in names {
+					// lists are written as JSON arrays, since definitions can contain any character
+					writer
+						.write_record([
+							name.username.clone(),
+							name.status.to_string(),
+							name.frequency.to_string(),
+							name.popularity.to_string(),
+							name.length.to_string(),
+							liked.unwrap_or(false).to_string(),
+							serde_json::to_string(&name.definition)
+								.expect("definitions to always be serializable"),
+							serde_json::to_string(&name.tags)
+								.expect("tags to always be serializable"),
+							name.verified_at.to_rfc3339(),
+							name.updated_at.to_rfc3339(),
+						])
+						.expect("writing to a vec to never fail");
+				}
+
+				writer.into_inner().expect("writing to a vec to never fail")
+			}
+			Format::Ndjson => {
+				let mut bytes = Vec::new();
+
+				for name in names {
+					serde_json::to_writer(&mut bytes, name)
+						.expect("name to always be serializable");
+					bytes.push(b'\n');
+				}
+
+				bytes
+			}
+		}
+	}
+}
+
+struct Export {
+	data: ViewNamesOptions,
+	user_id: i32,
+	format: Format,
+	sort: Sort,
+	pool: web::Data<PostgresPool>,
+	/// The sort key values of the last exported name, or `None` before the first batch
+	after: Option<Vec<Value>>,
+	/// The number of names that can still be exported, if `limit` was provided
+	remaining: Option<i64>,
+}
+
+impl Export {
+	/// Loads the next batch of names, returning an empty batch once every name has been exported.
+	fn next_batch(&mut self, connection: &mut PgConnection) -> Result<Vec<FormattedName>, Error> {
+		let limit = self
+			.remaining
+			.map_or(BATCH_SIZE, |remaining| remaining.min(BATCH_SIZE));
+
+		if limit <= 0 {
+			return Ok(Vec::new());
+		}
+
+		let mut names = order_names(self.data.filter.query(self.user_id), &self.sort).limit(limit);
+
+		names = match &self.after {
+			Some(values) => names.filter(self.sort.after(values)),
+			None => names.offset(self.data.offset.unwrap_or(0)),
+		};
+
+		let names = names
+			.select((
+				Name::as_select(),
+				liked(),
+				definition_rank(
+					schema::name::definition_search,
+					self.data.filter.definition_query.clone(),
+				)
+				.nullable(),
+			))
+			.load::<(Name, bool, Option<f64>)>(connection)?
+			.into_iter()
+			.map(FormattedName::from)
+			.collect::<Vec<_>>();
+
+		if let Some(last) = names.last() {
+			self.after = Some(self.sort.values(last));
+		}
+
+		if let Some(remaining) = self.remaining.as_mut() {
+			*remaining -= names.len() as i64;
+		}
+
+		Ok(names)
+	}
+}
+
+/// Streams every name matching the `POST /names` filters as CSV or NDJSON.
+///
+/// Names are loaded in batches using the same ordering as the listing, so the
+/// full result set is never held in memory. `limit` caps the number of names
+/// exported, and `cursor` or `offset` can be used to start part way through.
+#[post("/names/export")]
+pub async fn export_names(
+	query: web::Query<ExportNamesQuery>,
+	data: web::Json<ViewNamesOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let mut data = data.into_inner();
+	let format = query.format;
+
+	data.validate()?;
+
+	if data.filter.pattern.is_some() {
+		data = pool::run(&pool, move |connection| {
+			check_pattern(&data.filter, connection)?;
+
+			Ok(data)
+		})
+		.await?;
+	}
+
+	let sort = data.sort();
+
+	let after = data
+		.cursor
+		.as_deref()
+		.map(|cursor| {
+			sort.decode(cursor)
+				.ok_or_else(|| invalid("cursor", "is not a cursor for this sort"))
+		})
+		.transpose()?;
+
+	let export = Export {
+		remaining: data.limit,
+		data,
+		user_id: user.id,
+		format,
+		sort,
+		pool,
+		after,
+	};
+
+	let header = futures::stream::iter(format.header().map(|header| Ok(web::Bytes::from(header))));
+	let rows = futures::stream::unfold(Some(export), |export| async move {
+		let export = export?;
+
+		// each batch is loaded on the blocking thread pool, taking the export with it
+		let pool = export.pool.clone();
+		let (export, batch) = match pool::run(&pool, move |connection| {
+			let mut export = export;
+			let batch = export.next_batch(connection);
+
+			Ok((export, batch))
+		})
+		.await
+		{
+			Ok(result) => result,
+			Err(e) => return Some((Err(e), None)),
+		};
+
+		match batch {
+			Ok(names) if names.is_empty() => None,
+			Ok(names) => {
+				let bytes = web::Bytes::from(export.format.write(&names));
+
+				Some((Ok(bytes), Some(export)))
+			}
+			// end the stream after an error, which aborts the response
+			Err(e) => Some((Err(e), None)),
+		}
+	});
+
+	Ok(HttpResponse::Ok()
+		.content_type(format.content_type())
+		.insert_header((
+			header::CONTENT_DISPOSITION,
+			format!("attachment; filename=\"names.{}\"", format.extension()),
+		))
+		.streaming(futures::StreamExt::chain(header, rows)))
+}
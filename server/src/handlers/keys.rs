@@ -0,0 +1,144 @@
+use actix_web::{delete, get, post, web, HttpResponse};
+use database::{keys, models::ApiKey, schema, PostgresPool};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use crate::{auth::User, error::Error, pool};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateKeyOptions {
+	pub label: String,
+	pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedKey {
+	pub id: i32,
+	pub label: String,
+	pub prefix: String,
+	pub created_at: chrono::DateTime<chrono::Utc>,
+	pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+	pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+	pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+	/// Whether this is the key used to make the request
+	pub current: bool,
+}
+
+#[derive(Serialize)]
+pub struct ViewKeysResponse {
+	pub data: Vec<FormattedKey>,
+}
+
+#[derive(Serialize)]
+pub struct CreateKeyResponse {
+	#[serde(flatten)]
+	pub data: FormattedKey,
+	/// The plaintext key, which is only ever returned once
+	pub key: String,
+}
+
+#[derive(Serialize)]
+pub struct KeyResponse {
+	pub updated: bool,
+}
+
+impl FormattedKey {
+	fn new(key: ApiKey, user: &User) -> Self {
+		Self {
+			current: key.id == user.key_id,
+			id: key.id,
+			label: key.label,
+			prefix: key.prefix,
+			created_at: key.created_at,
+			last_used_at: key.last_used_at,
+			expires_at: key.expires_at,
+			revoked_at: key.revoked_at,
+		}
+	}
+}
+
+#[get("/keys")]
+pub async fn view_keys(user: User, pool: web::Data<PostgresPool>) -> Result<HttpResponse, Error> {
+	let user_id = user.id;
+
+	let keys = pool::run(&pool, move |connection| {
+		Ok(schema::api_key::table
+			.filter(schema::api_key::user_id.eq(user_id))
+			.order(schema::api_key::created_at.desc())
+			.load::<ApiKey>(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(ViewKeysResponse {
+		data: keys
+			.into_iter()
+			.map(|key| FormattedKey::new(key, &user))
+			.collect(),
+	}))
+}
+
+#[post("/keys")]
+pub async fn create_key(
+	data: web::Json<CreateKeyOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let user_id = user.id;
+	let data = data.into_inner();
+
+	let (api_key, key) = pool::run(&pool, move |connection| {
+		Ok(keys::issue(
+			connection,
+			user_id,
+			&data.label,
+			data.expires_at,
+		)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Created().json(CreateKeyResponse {
+		data: FormattedKey::new(api_key, &user),
+		key,
+	}))
+}
+
+#[post("/keys/{id}/rotate")]
+pub async fn rotate_key(
+	id: web::Path<i32>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let (id, user_id) = (id.into_inner(), user.id);
+
+	let (api_key, key) = pool::run(&pool, move |connection| {
+		keys::rotate(connection, id, Some(user_id))?.ok_or(Error::NotFound)
+	})
+	.await?;
+
+	Ok(HttpResponse::Created().json(CreateKeyResponse {
+		data: FormattedKey::new(api_key, &user),
+		key,
+	}))
+}
+
+#[delete("/keys/{id}")]
+pub async fn revoke_key(
+	id: web::Path<i32>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let (id, user_id) = (id.into_inner(), user.id);
+
+	pool::run(&pool, move |connection| {
+		if keys::revoke(connection, id, Some(user_id))? {
+			Ok(())
+		} else {
+			Err(Error::NotFound)
+		}
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(KeyResponse { updated: true }))
+}
@@ -0,0 +1,280 @@
+use actix_web::{delete, get, put, web, HttpResponse};
+use database::{
+	functions::coalesce,
+	models::{Like, Name},
+	schema, PostgresPool,
+};
+use diesel::{
+	sql_types::{Integer, Nullable, Text},
+	upsert::excluded,
+	ExpressionMethods, IntoSql, JoinOnDsl, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	auth::User,
+	error::{invalid, Error},
+	handlers::names::NameResponse,
+	pool,
+};
+
+/// The most likes returned by a single request
+const MAX_LIMIT: i64 = 500;
+/// The most names that can be liked or unliked in a single request
+const MAX_BULK_NAMES: usize = 500;
+/// The longest note accepted on a like
+const MAX_NOTE_LENGTH: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct LikeOptions {
+	/// Replaces the note on the like, or leaves it as it is if not provided
+	pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BulkLikeOptions {
+	pub usernames: Vec<String>,
+	/// The note set on every like, leaving existing notes as they are if not provided
+	pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BulkUnlikeOptions {
+	pub usernames: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ViewLikesOptions {
+	pub limit: Option<i64>,
+	pub offset: Option<i64>,
+}
+
+/// A liked name along with its current status.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedLike {
+	pub username: String,
+	pub note: Option<String>,
+	pub created_at: chrono::DateTime<chrono::Utc>,
+	/// The status of the name, or `None` if it is no longer tracked
+	pub status: Option<i16>,
+	pub frequency: Option<f64>,
+	pub verified_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<(Like, Option<Name>)> for FormattedLike {
+	fn from((like, name): (Like, Option<Name>)) -> Self {
+		Self {
+			username: like.username,
+			note: like.note,
+			created_at: like.created_at,
+			status: name.as_ref().map(|name| name.status),
+			frequency: name.as_ref().map(|name| name.frequency),
+			verified_at: name.map(|name| name.verified_at),
+		}
+	}
+}
+
+#[derive(Serialize)]
+pub struct ViewLikesResponse {
+	pub data: Vec<FormattedLike>,
+}
+
+#[derive(Serialize)]
+pub struct BulkLikeResponse {
+	/// The number of names liked or unliked
+	pub count: usize,
+}
+
+fn validate_note(note: Option<&str>) -> Result<(), Error> {
+	if note.is_some_and(|note| note.chars().count() > MAX_NOTE_LENGTH) {
+		return Err(invalid("note", "must be at most 1000 characters"));
+	}
+
+	Ok(())
+}
+
+/// Returns the usernames as they are stored, without duplicates.
+fn prepare_usernames(usernames: &[String]) -> Result<Vec<String>, Error> {
+	if usernames.is_empty() || usernames.len() > MAX_BULK_NAMES {
+		return Err(invalid("usernames", "must contain between 1 and 500 names"));
+	}
+
+	let mut usernames = usernames
+		.iter()
+		.map(|username| username.to_ascii_lowercase())
+		.collect::<Vec<_>>();
+
+	usernames.sort_unstable();
+	usernames.dedup();
+
+	Ok(usernames)
+}
+
+/// Likes every name in `usernames` that exists, replacing the note on names that
+/// are already liked if `note` is provided. Returns the number of names liked.
+fn like_names(
+	connection: &mut diesel::PgConnection,
+	user_id: i32,
+	usernames: &[String],
+	note: Option<&str>,
+) -> diesel::QueryResult<usize> {
+	diesel::insert_into(schema::like::table)
+		.values(
+			schema::name::table
+				.filter(schema::name::username.eq_any(usernames))
+				.select((
+					schema::name::username,
+					user_id.into_sql::<Integer>(),
+					note.into_sql::<Nullable<Text>>(),
+				)),
+		)
+		.into_columns((
+			schema::like::username,
+			schema::like::user_id,
+			schema::like::note,
+		))
+		.on_conflict((schema::like::username, schema::like::user_id))
+		.do_update()
+		.set(schema::like::note.eq(coalesce(excluded(schema::like::note), schema::like::note)))
+		.execute(connection)
+}
+
+#[get("/likes")]
+pub async fn view_likes(
+	options: web::Query<ViewLikesOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	if options.offset.is_some_and(|offset| offset < 0) {
+		return Err(invalid("offset", "must not be negative"));
+	}
+
+	let options = options.into_inner();
+
+	let likes = pool::run(&pool, move |connection| {
+		Ok(schema::like::table
+			.left_join(schema::name::table.on(schema::name::username.eq(schema::like::username)))
+			.filter(schema::like::user_id.eq(user.id))
+			.select((Like::as_select(), Option::<Name>::as_select()))
+			.order((
+				schema::like::created_at.desc(),
+				schema::like::username.asc(),
+			))
+			.limit(options.limit.unwrap_or(50).clamp(0, MAX_LIMIT))
+			.offset(options.offset.unwrap_or(0))
+			.load::<(Like, Option<Name>)>(connection)?
+			.into_iter()
+			.map(FormattedLike::from)
+			.collect::<Vec<_>>())
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(ViewLikesResponse { data: likes }))
+}
+
+#[put("/likes")]
+pub async fn like_names_bulk(
+	data: web::Json<BulkLikeOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let usernames = prepare_usernames(&data.usernames)?;
+
+	validate_note(data.note.as_deref())?;
+
+	let note = data.into_inner().note;
+
+	let count = pool::run(&pool, move |connection| {
+		Ok(like_names(
+			connection,
+			user.id,
+			&usernames,
+			note.as_deref(),
+		)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(BulkLikeResponse { count }))
+}
+
+#[delete("/likes")]
+pub async fn unlike_names_bulk(
+	data: web::Json<BulkUnlikeOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let usernames = prepare_usernames(&data.usernames)?;
+
+	let count = pool::run(&pool, move |connection| {
+		Ok(diesel::delete(schema::like::table)
+			.filter(schema::like::username.eq_any(&usernames))
+			.filter(schema::like::user_id.eq(user.id))
+			.execute(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(BulkLikeResponse { count }))
+}
+
+/// Likes a name, with a JSON body that can contain a note, such as `{}` for no note.
+#[put("/names/{name}/like")]
+pub async fn like_name(
+	name: web::Path<String>,
+	options: web::Json<LikeOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let options = options.into_inner();
+
+	validate_note(options.note.as_deref())?;
+
+	let name = name.into_inner().to_ascii_lowercase();
+
+	let like = pool::run(&pool, move |connection| {
+		// the name is only liked if it exists
+		if like_names(
+			connection,
+			user.id,
+			std::slice::from_ref(&name),
+			options.note.as_deref(),
+		)? == 0
+		{
+			return Err(Error::NotFound);
+		}
+
+		Ok(schema::like::table
+			.left_join(schema::name::table.on(schema::name::username.eq(schema::like::username)))
+			.filter(schema::like::user_id.eq(user.id))
+			.filter(schema::like::username.eq(&name))
+			.select((Like::as_select(), Option::<Name>::as_select()))
+			.get_result::<(Like, Option<Name>)>(connection)
+			.map(FormattedLike::from)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(like))
+}
+
+#[delete("/names/{name}/like")]
+pub async fn unlike_name(
+	name: web::Path<String>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let name = name.into_inner().to_ascii_lowercase();
+
+	let updates = pool::run(&pool, move |connection| {
+		Ok(diesel::delete(schema::like::table)
+			.filter(schema::like::username.eq(name))
+			.filter(schema::like::user_id.eq(user.id))
+			.execute(connection)?)
+	})
+	.await?;
+
+	if updates == 0 {
+		return Err(Error::NotFound);
+	}
+
+	Ok(HttpResponse::Ok().json(NameResponse { updated: true }))
+}
@@ -1,2 +1,14 @@
+pub mod accounts;
+pub mod alerts;
+pub mod export;
+pub mod keys;
+pub mod likes;
+pub mod monitoring;
 pub mod names;
+pub mod proxies;
+pub mod searches;
 pub mod snipe;
+pub mod stats;
+pub mod stream;
+pub mod tags;
+pub mod webhooks;
@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use actix_web::{get, web, HttpResponse};
+use database::{migrations, PostgresPool};
+use diesel::{connection::SimpleConnection, PgConnection};
+use metrics::server::{POOL_CONNECTIONS, POOL_IDLE_CONNECTIONS, POOL_MAX_CONNECTIONS};
+use serde::Serialize;
+
+/// How long readiness waits for a pooled connection before reporting the database as down
+const READY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Set once the server starts shutting down, so that it stops being reported as ready
+/// while in-flight requests are drained.
+#[derive(Default)]
+pub struct ShuttingDown(AtomicBool);
+
+impl ShuttingDown {
+	pub fn begin(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	fn get(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+#[derive(Serialize)]
+pub struct HealthResponse {
+	pub status: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessResponse {
+	pub ready: bool,
+	pub shutting_down: bool,
+	/// Whether a pooled connection could run a query
+	pub database: bool,
+	/// Whether every migration the server was built with has been applied
+	pub migrations: bool,
+}
+
+/// Returns `(database, migrations)` for a pooled connection.
+fn check(connection: &mut PgConnection) -> (bool, bool) {
+	if connection.batch_execute("SELECT 1").is_err() {
+		return (false, false);
+	}
+
+	(true, migrations::is_current(connection).unwrap_or(false))
+}
+
+/// Reports that the process is running, without checking its dependencies.
+#[get("/healthz")]
+pub async fn healthz() -> HttpResponse {
+	HttpResponse::Ok().json(HealthResponse { status: "ok" })
+}
+
+/// Reports whether the server can handle requests, which requires a working
+/// database connection with every migration applied.
+#[get("/readyz")]
+pub async fn readyz(
+	pool: web::Data<PostgresPool>,
+	shutting_down: web::Data<ShuttingDown>,
+) -> HttpResponse {
+	let shutting_down = shutting_down.get();
+	let (database, migrations) = web::block(move || match pool.get_timeout(READY_TIMEOUT) {
+		Ok(mut connection) => check(&mut connection),
+		Err(_) => (false, false),
+	})
+	.await
+	.unwrap_or((false, false));
+	let ready = !shutting_down && database && migrations;
+
+	let mut response = if ready {
+		HttpResponse::Ok()
+	} else {
+		HttpResponse::ServiceUnavailable()
+	};
+
+	response.json(ReadinessResponse {
+		ready,
+		shutting_down,
+		database,
+		migrations,
+	})
+}
+
+#[get("/metrics")]
+pub async fn view_metrics(pool: web::Data<PostgresPool>) -> HttpResponse {
+	// the pool is only sampled when scraped, since it has no hooks to observe changes
+	let state = pool.state();
+
+	POOL_CONNECTIONS.set(state.connections.into());
+	POOL_IDLE_CONNECTIONS.set(state.idle_connections.into());
+	POOL_MAX_CONNECTIONS.set(pool.max_size().into());
+
+	HttpResponse::Ok()
+		.content_type(metrics::content_type())
+		.body(metrics::gather())
+}
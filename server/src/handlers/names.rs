@@ -1,304 +1,618 @@
-use actix_web::{get, http::header, post, web, HttpRequest, HttpResponse};
-use database::{schema, PostgresPool, Status};
+use std::{
+	collections::{HashMap, HashSet},
+	hash::{DefaultHasher, Hash, Hasher},
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use actix_web::{
+	get,
+	http::header::{self, ContentType},
+	post, web, HttpRequest, HttpResponse,
+};
+use database::{
+	filter::{liked, BoxedNames, NameFilter},
+	functions::definition_rank,
+	models::{Name, NameEvent},
+	schema, PostgresPool, Source, Status,
+};
 use diesel::prelude::*;
-use diesel::Queryable;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+	auth::User,
+	cursor::{self, Column, Direction, Sort, SortKey},
+	error::{invalid, Error},
+	handlers::snipe::FormattedSnipe,
+	pool,
+};
+
+/// How long a page of names is served from the cache
+const CACHE_TTL: Duration = Duration::from_secs(30);
+/// The most pages kept in the cache at once
+const MAX_CACHED_PAGES: usize = 1_000;
+
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ViewNamesOptions {
 	pub limit: Option<i64>,
 	pub offset: Option<i64>,
-	pub sort: Option<String>,
-	pub column: Option<String>,
-	pub search: Option<String>,
-	pub tags: Option<Vec<String>>,
-	pub from: Option<chrono::NaiveDateTime>,
-	pub to: Option<chrono::NaiveDateTime>,
+	/// The direction to sort `column` in, which is ignored without `column`
+	#[serde(default, deserialize_with = "cursor::lenient")]
+	pub sort: Option<Direction>,
+	#[serde(default, deserialize_with = "cursor::lenient")]
+	pub column: Option<Column>,
+	/// The keys to sort by, in order of precedence
+	pub order_by: Option<Vec<SortKey>>,
+	#[serde(flatten)]
+	pub filter: NameFilter,
+	/// An opaque cursor returned by a previous request, used instead of `offset`
+	pub cursor: Option<String>,
+	#[serde(default)]
+	pub count: Count,
 }
 
-#[derive(Queryable, Serialize)]
-#[serde(rename_all = "camelCase")]
+impl ViewNamesOptions {
+	/// Rejects options that are well-formed but cannot be used together.
+	///
+	/// # Errors
+	/// Returns a 422 describing the first invalid field.
+	pub fn validate(&self) -> Result<(), Error> {
+		// a page without names has no name to build the cursor for the next page from
+		if self.limit.is_some_and(|limit| limit < 1) {
+			return Err(invalid("limit", "must be at least 1"));
+		}
+
+		if self.offset.is_some_and(|offset| offset < 0) {
+			return Err(invalid("offset", "must not be negative"));
+		}
+
+		if let Some(order_by) = &self.order_by {
+			if self.sort.is_some() || self.column.is_some() {
+				return Err(invalid("orderBy", "cannot be used with sort or column"));
+			}
+
+			if order_by.is_empty() {
+				return Err(invalid("orderBy", "must contain at least one key"));
+			}
+
+			for (i, key) in order_by.iter().enumerate() {
+				if order_by[..i].iter().any(|other| other.column == key.column) {
+					return Err(invalid("orderBy", "columns must be unique"));
+				}
+			}
+		}
+
+		if self.filter.definition_query.is_none()
+			&& (self.column == Some(Column::Rank)
+				|| self
+					.order_by
+					.iter()
+					.flatten()
+					.any(|key| key.column == Column::Rank))
+		{
+			return Err(invalid("definitionQuery", "is required to sort by rank"));
+		}
+
+		Ok(self.filter.validate()?)
+	}
+
+	pub fn sort(&self) -> Sort {
+		match &self.order_by {
+			Some(order_by) => Sort::from_keys(order_by),
+			None => Sort::new(self.sort, self.column),
+		}
+		.ranked_by(self.filter.definition_query.clone())
+	}
+}
+
+/// How the total number of matching names should be computed.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Count {
+	/// Run a `COUNT(*)` over the matching names
+	#[default]
+	Exact,
+	/// Use the query planner's estimate
+	Approximate,
+	/// Do not compute a total
+	None,
+}
+
+/// A name in a listing, loaded with [`FormattedName::columns`].
+#[derive(Serialize, Clone)]
 pub struct FormattedName {
-	pub username: String,
-	pub frequency: f64,
-	pub definition: Vec<String>,
-	pub tags: Vec<String>,
-	pub verified_at: chrono::DateTime<chrono::Utc>,
-	pub updated_at: chrono::DateTime<chrono::Utc>,
-	pub status: i16,
+	#[serde(flatten)]
+	pub name: Name,
 	pub liked: Option<bool>,
+	/// How well the definitions match `definitionQuery`, if one was provided
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub rank: Option<f64>,
 }
 
-#[derive(Serialize)]
+impl From<(Name, bool, Option<f64>)> for FormattedName {
+	fn from((name, liked, rank): (Name, bool, Option<f64>)) -> Self {
+		Self {
+			name,
+			liked: Some(liked),
+			rank,
+		}
+	}
+}
+
+#[derive(Serialize, Clone)]
 pub struct ViewNamesResponse {
 	pub data: Vec<FormattedName>,
-	pub total: i64,
+	pub total: Option<i64>,
+	/// The cursor for the next page, or `None` if this is the last page
+	pub cursor: Option<String>,
 }
 
 #[derive(Serialize)]
-pub struct NameResponse {
-	pub updated: bool,
+pub struct NameDetailsResponse {
+	#[serde(flatten)]
+	pub name: Name,
+	pub liked: bool,
+	pub snipe: Option<FormattedSnipe>,
 }
 
-#[post("/names")]
-pub async fn view_names(
-	data: web::Json<ViewNamesOptions>,
-	req: HttpRequest,
-	pool: web::Data<PostgresPool>,
-) -> Result<HttpResponse, actix_web::Error> {
-	// get the token from the "Authorization" header, or return a 401 if it does not exist or cannot be parsed
-	let token = req
-		.headers()
-		.get(header::AUTHORIZATION)
-		.ok_or(actix_web::error::ErrorUnauthorized(""))?
-		.to_str()
-		.map_err(|_| actix_web::error::ErrorUnauthorized(""))?;
-
-	let connection = &mut pool
-		.get()
-		.map_err(|_| actix_web::error::ErrorInternalServerError(""))?;
-
-	// get the user from the database, or return a 401 if the token is invalid
-	let user_id = schema::user::table
-		.select(schema::user::id)
-		.filter(schema::user::key.eq(token))
-		.get_result::<i32>(connection);
-
-	let user_id = match user_id {
-		Ok(user_id) => user_id,
-		Err(_) => return Err(actix_web::error::ErrorUnauthorized("")),
-	};
-
-	let query = || {
-		let mut names = schema::name::table.into_boxed().left_join(
-			schema::like::table.on(schema::like::username
-				.eq(schema::name::username)
-				.and(schema::like::user_id.eq(user_id))),
-		);
+/// The most history events returned by a single request
+const MAX_HISTORY_LIMIT: i64 = 500;
 
-		if let Some(search) = &data.search {
-			names = names
-				.filter(schema::name::username.like(format!("%{}%", search.to_ascii_lowercase())))
-		}
+#[derive(Deserialize)]
+pub struct ViewNameHistoryOptions {
+	pub limit: Option<i64>,
+	/// Only return events with an id greater than this, to fetch the next page
+	pub after: Option<i64>,
+}
 
-		if data
-			.tags
-			.as_ref()
-			.map(|tags| tags.contains(&"new".to_string()))
-			.unwrap_or(false)
-		{
-			// filter for updated_at to be within the last 24 hours
-			names = names.filter(schema::name::updated_at.ge(chrono::Utc::now().naive_utc()
-				- chrono::Duration::try_days(1).expect("1 day to be less than i64::MAX / 1_000")));
-		} else if let Some(from) = data.from {
-			names = names.filter(schema::name::updated_at.ge(from));
-		}
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedNameEvent {
+	pub id: i64,
+	pub old_status: Status,
+	pub new_status: Status,
+	pub source: Source,
+	pub created_at: chrono::DateTime<chrono::Utc>,
+}
 
-		if let Some(to) = data.to {
-			names = names.filter(schema::name::updated_at.le(to));
+impl From<NameEvent> for FormattedNameEvent {
+	fn from(event: NameEvent) -> Self {
+		Self {
+			id: event.id,
+			old_status: event.old_status.into(),
+			new_status: event.new_status.into(),
+			source: event.source.into(),
+			created_at: event.created_at,
 		}
+	}
+}
 
-		let mut other_tags = Vec::new();
-		let mut has_taken_tag = false;
-
-		if let Some(tags) = data.tags.as_ref() {
-			for tag in tags {
-				match tag.as_str() {
-					"common" => names = names.filter(schema::name::frequency.ge(0.5)),
-					"short" => names = names.filter(schema::name::length.le(7)),
-					"liked" => {
-						names = names.filter(schema::like::username.is_not_null());
-					}
-					"taken" => {
-						names = names
-							.filter(schema::name::status.ne(i16::from(Status::BatchAvailable)));
-						has_taken_tag = true;
-					}
-					"banned" => {
-						names = names.filter(schema::name::status.eq(i16::from(Status::Banned)));
-						has_taken_tag = true;
-					}
-					"name" => names = names.filter(schema::name::tags.contains(vec!["name"])),
-					tag => other_tags.push(tag),
-				}
-			}
-		}
+#[derive(Serialize)]
+pub struct NameHistoryResponse {
+	pub data: Vec<FormattedNameEvent>,
+}
 
-		if !has_taken_tag {
-			names = names.filter(schema::name::status.eq(i16::from(Status::Available)));
-		}
+#[derive(Serialize)]
+pub struct NameResponse {
+	pub updated: bool,
+}
 
-		if !other_tags.is_empty() {
-			names = names.filter(schema::name::tags.contains(other_tags));
+/// Rejects a filter with a regular expression that Postgres does not accept.
+pub fn check_pattern(filter: &NameFilter, connection: &mut PgConnection) -> Result<(), Error> {
+	match &filter.pattern {
+		Some(pattern) if !pattern.is_valid(connection) => {
+			Err(invalid("pattern", "is not a valid regular expression"))
 		}
+		_ => Ok(()),
+	}
+}
 
-		names
-	};
-
-	let mut names = query()
-		.limit(data.limit.unwrap_or(10))
-		.offset(data.offset.unwrap_or(0))
-		.select((
-			schema::name::username,
-			schema::name::frequency,
-			schema::name::definition,
-			schema::name::tags,
-			schema::name::verified_at,
-			schema::name::updated_at,
-			schema::name::status,
-			// TRUE if it is liked, FALSE otherwise -- default to FALSE if the value is NULL
-			schema::like::username
-				.is_not_null()
-				.nullable()
-				.or(false.into_sql::<diesel::sql_types::Bool>()),
-		));
-
-	let names = {
-		match (data.sort.as_deref(), data.column.as_deref()) {
-			(Some("asc"), Some("frequency")) => {
-				names = names.order(schema::name::frequency.asc());
+/// Orders `names` by every key of `sort`.
+pub fn order_names<'a>(mut names: BoxedNames<'a>, sort: &Sort) -> BoxedNames<'a> {
+	for (column, direction) in sort.keys() {
+		names = match (column, direction) {
+			(Column::Frequency, Direction::Asc) => {
+				names.then_order_by(schema::name::frequency.asc())
 			}
-			(Some("asc"), Some("length")) => {
-				names = names.order((schema::name::length.asc(), schema::name::frequency.desc()))
+			(Column::Frequency, Direction::Desc) => {
+				names.then_order_by(schema::name::frequency.desc())
 			}
-			(_, Some("length")) => {
-				names = names.order((schema::name::length.desc(), schema::name::frequency.desc()))
+			(Column::Popularity, Direction::Asc) => {
+				names.then_order_by(schema::name::popularity.asc())
 			}
-			(Some("asc"), Some("updatedAt")) => {
-				names = names.order((
-					schema::name::updated_at.asc(),
-					schema::name::frequency.desc(),
-				))
+			(Column::Popularity, Direction::Desc) => {
+				names.then_order_by(schema::name::popularity.desc())
 			}
-			(_, Some("updatedAt")) => {
-				names = names.order((
-					schema::name::updated_at.desc(),
-					schema::name::frequency.desc(),
-				))
+			(Column::Length, Direction::Asc) => names.then_order_by(schema::name::length.asc()),
+			(Column::Length, Direction::Desc) => names.then_order_by(schema::name::length.desc()),
+			(Column::UpdatedAt, Direction::Asc) => {
+				names.then_order_by(schema::name::updated_at.asc())
 			}
-			(Some("asc"), Some("verifiedAt")) => {
-				names = names.order((
-					schema::name::verified_at.asc(),
-					schema::name::frequency.desc(),
-				))
+			(Column::UpdatedAt, Direction::Desc) => {
+				names.then_order_by(schema::name::updated_at.desc())
 			}
-			(_, Some("verifiedAt")) => {
-				names = names.order((
-					schema::name::verified_at.desc(),
-					schema::name::frequency.desc(),
-				))
+			(Column::VerifiedAt, Direction::Asc) => {
+				names.then_order_by(schema::name::verified_at.asc())
 			}
-			(Some("asc"), Some("username")) => {
-				names = names.order((schema::name::username.asc(), schema::name::frequency.desc()))
+			(Column::VerifiedAt, Direction::Desc) => {
+				names.then_order_by(schema::name::verified_at.desc())
 			}
-			(_, Some("username")) => {
-				names = names.order((
-					schema::name::username.desc(),
-					schema::name::frequency.desc(),
-				))
+			(Column::Username, Direction::Asc) => names.then_order_by(schema::name::username.asc()),
+			(Column::Username, Direction::Desc) => {
+				names.then_order_by(schema::name::username.desc())
 			}
-			_ => names = names.order(schema::name::frequency.desc()),
+			(Column::Rank, Direction::Asc) => names.then_order_by(
+				definition_rank(
+					schema::name::definition_search,
+					sort.query().map(str::to_string),
+				)
+				.asc(),
+			),
+			(Column::Rank, Direction::Desc) => names.then_order_by(
+				definition_rank(
+					schema::name::definition_search,
+					sort.query().map(str::to_string),
+				)
+				.desc(),
+			),
+		};
+	}
+
+	names
+}
+
+/// The options of a listing in a normalized form, used as the key of the [`NameCache`].
+#[derive(Serialize)]
+struct CacheKey<'a> {
+	filter: NameFilter,
+	sort: String,
+	limit: i64,
+	offset: i64,
+	cursor: Option<&'a str>,
+	count: Count,
+}
+
+impl ViewNamesOptions {
+	/// Returns the key this listing is cached under, or `None` if it cannot be cached
+	/// because the names depend on the user.
+	fn cache_key(&self, sort: &Sort, limit: i64) -> Option<String> {
+		if self.filter.is_personal() {
+			return None;
 		}
 
-		names
-	};
+		let key = CacheKey {
+			filter: self.filter.normalized(),
+			sort: sort.signature(),
+			limit,
+			// the offset is ignored when a cursor is provided
+			offset: if self.cursor.is_some() {
+				0
+			} else {
+				self.offset.unwrap_or(0)
+			},
+			cursor: self.cursor.as_deref(),
+			count: self.count,
+		};
+
+		Some(serde_json::to_string(&key).expect("cache key to always be serializable"))
+	}
+}
+
+#[derive(Default)]
+struct CachedPages {
+	pages: HashMap<String, (Instant, Arc<ViewNamesResponse>)>,
+	/// Incremented whenever the cache is cleared, so that pages loaded before then are not stored
+	generation: u64,
+}
 
-	let names = names
-		.load::<FormattedName>(connection)
-		.map_err(|_| actix_web::error::ErrorInternalServerError(""))?;
+/// Recently listed pages of names, keyed by their normalized options.
+///
+/// Pages are stored without `liked`, since it differs between users, and every page
+/// is dropped whenever the status of a name changes.
+#[derive(Default)]
+pub struct NameCache(Mutex<CachedPages>);
+
+impl NameCache {
+	fn lock(&self) -> std::sync::MutexGuard<'_, CachedPages> {
+		self.0.lock().expect("name cache lock to not be poisoned")
+	}
+
+	/// Returns the cached page for `key` if it has not expired.
+	fn get(&self, key: &str) -> Option<Arc<ViewNamesResponse>> {
+		self.lock()
+			.pages
+			.get(key)
+			.filter(|(cached_at, _)| cached_at.elapsed() < CACHE_TTL)
+			.map(|(_, page)| page.clone())
+	}
+
+	fn generation(&self) -> u64 {
+		self.lock().generation
+	}
+
+	/// Stores a page loaded when the cache was at `generation`, unless it has been cleared since.
+	fn insert(&self, key: String, generation: u64, page: ViewNamesResponse) {
+		let mut cache = self.lock();
+
+		if cache.generation != generation {
+			return;
+		}
 
-	let count = query()
-		.count()
-		.get_result::<i64>(connection)
-		.map_err(|_| actix_web::error::ErrorInternalServerError(""))?;
+		if cache.pages.len() >= MAX_CACHED_PAGES {
+			cache
+				.pages
+				.retain(|_, (cached_at, _)| cached_at.elapsed() < CACHE_TTL);
+		}
 
-	Ok(HttpResponse::Ok().json(ViewNamesResponse {
-		data: names,
-		total: count,
-	}))
+		// every page is still fresh, so make room by dropping the oldest one
+		if cache.pages.len() >= MAX_CACHED_PAGES {
+			if let Some(oldest) = cache
+				.pages
+				.iter()
+				.min_by_key(|(_, (cached_at, _))| *cached_at)
+				.map(|(key, _)| key.clone())
+			{
+				cache.pages.remove(&oldest);
+			}
+		}
+
+		cache.pages.insert(key, (Instant::now(), Arc::new(page)));
+	}
+
+	/// Drops every cached page.
+	pub fn clear(&self) {
+		let mut cache = self.lock();
+
+		cache.pages.clear();
+		cache.generation += 1;
+	}
 }
 
-#[get("/names/{name}/like")]
-pub async fn like_name(
-	name: web::Path<String>,
+/// Returns a strong entity tag for a response body.
+fn entity_tag(body: &[u8]) -> String {
+	let mut hasher = DefaultHasher::new();
+	body.hash(&mut hasher);
+
+	format!("\"{:016x}\"", hasher.finish())
+}
+
+/// Returns `true` if the `If-None-Match` header of the request matches `etag`.
+fn is_not_modified(req: &HttpRequest, etag: &str) -> bool {
+	req.headers()
+		.get_all(header::IF_NONE_MATCH)
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|value| value.split(','))
+		.map(|tag| {
+			let tag = tag.trim();
+
+			// weak comparison is used, as for any conditional GET
+			tag.strip_prefix("W/").unwrap_or(tag)
+		})
+		.any(|tag| tag == "*" || tag == etag)
+}
+
+/// Lists names matching the filters. Pages that do not depend on the user are
+/// cached for a short time, and the response has an `ETag` so that an unchanged
+/// page can be revalidated with `If-None-Match`.
+#[post("/names")]
+pub async fn view_names(
 	req: HttpRequest,
+	data: web::Json<ViewNamesOptions>,
+	user: User,
 	pool: web::Data<PostgresPool>,
-) -> Result<HttpResponse, actix_web::Error> {
-	let token = req
-		.headers()
-		.get(header::AUTHORIZATION)
-		.ok_or(actix_web::error::ErrorUnauthorized(""))?
-		.to_str()
-		.map_err(|_| actix_web::error::ErrorUnauthorized(""))?;
-
-	let connection = &mut pool
-		.get()
-		.map_err(|_| actix_web::error::ErrorInternalServerError(""))?;
-
-	let user_id = schema::user::table
-		.select(schema::user::id)
-		.filter(schema::user::key.eq(token))
-		.get_result::<i32>(connection);
-
-	let user_id = match user_id {
-		Ok(user_id) => user_id,
-		Err(_) => return Err(actix_web::error::ErrorUnauthorized("")),
-	};
+	cache: web::Data<NameCache>,
+) -> Result<HttpResponse, Error> {
+	let data = data.into_inner();
+
+	data.validate()?;
+
+	let sort = data.sort();
+	let limit = data.limit.unwrap_or(10);
+	let after = data
+		.cursor
+		.as_deref()
+		.map(|cursor| {
+			sort.decode(cursor)
+				.ok_or_else(|| invalid("cursor", "is not a cursor for this sort"))
+		})
+		.transpose()?;
+	let key = data.cache_key(&sort, limit);
+
+	let response = match key.as_deref().and_then(|key| cache.get(key)) {
+		Some(page) => {
+			let mut response = ViewNamesResponse::clone(&page);
+			let usernames = response
+				.data
+				.iter()
+				.map(|name| name.name.username.clone())
+				.collect::<Vec<_>>();
+
+			// the cached page is shared between users, so their likes are merged in afterwards
+			let liked = pool::run(&pool, move |connection| {
+				Ok(schema::like::table
+					.select(schema::like::username)
+					.filter(schema::like::user_id.eq(user.id))
+					.filter(schema::like::username.eq_any(usernames))
+					.load::<String>(connection)?)
+			})
+			.await?
+			.into_iter()
+			.collect::<HashSet<_>>();
+
+			for name in &mut response.data {
+				name.liked = Some(liked.contains(&name.name.username));
+			}
+
+			response
+		}
+		None => {
+			let generation = cache.generation();
+
+			let (mut names, count, sort) = pool::run(&pool, move |connection| {
+				check_pattern(&data.filter, connection)?;
+
+				// fetch one extra row to know whether there is another page
+				let mut names = order_names(data.filter.query(user.id), &sort).limit(limit + 1);
+
+				names = match &after {
+					Some(values) => names.filter(sort.after(values)),
+					None => names.offset(data.offset.unwrap_or(0)),
+				};
+
+				let names = names
+					.select((
+						Name::as_select(),
+						liked(),
+						definition_rank(
+							schema::name::definition_search,
+							data.filter.definition_query.clone(),
+						)
+						.nullable(),
+					))
+					.load::<(Name, bool, Option<f64>)>(connection)?
+					.into_iter()
+					.map(FormattedName::from)
+					.collect::<Vec<_>>();
+
+				let count = match data.count {
+					Count::Exact => Some(
+						data.filter
+							.query(user.id)
+							.count()
+							.get_result::<i64>(connection)?,
+					),
+					Count::Approximate => Some(database::explain::estimate_rows(
+						data.filter.query(user.id).select(schema::name::username),
+						connection,
+					)?),
+					Count::None => None,
+				};
+
+				Ok((names, count, sort))
+			})
+			.await?;
+
+			let cursor = if names.len() as i64 > limit {
+				names.truncate(limit as usize);
+				names.last().map(|name| sort.encode(name))
+			} else {
+				None
+			};
+
+			let response = ViewNamesResponse {
+				data: names,
+				total: count,
+				cursor,
+			};
+
+			if let Some(key) = key {
+				let mut page = response.clone();
+
+				for name in &mut page.data {
+					name.liked = None;
+				}
 
-	// insert into likes (username, user_id) values ($1, $2) on conflict do nothing
-	// get the user_id from the token in the same query
+				cache.insert(key, generation, page);
+			}
 
-	let updates = diesel::insert_into(schema::like::table)
-		.values((
-			schema::like::username.eq(name.into_inner()),
-			schema::like::user_id.eq(user_id),
-		))
-		.on_conflict_do_nothing()
-		.execute(connection)
-		.map_err(|_| actix_web::error::ErrorInternalServerError(""))?;
+			response
+		}
+	};
 
-	Ok(HttpResponse::Ok().json(NameResponse {
-		updated: updates > 0,
+	let body = serde_json::to_vec(&response).expect("names to always be serializable");
+	let etag = entity_tag(&body);
+
+	if is_not_modified(&req, &etag) {
+		return Ok(HttpResponse::NotModified()
+			.insert_header((header::ETAG, etag))
+			.finish());
+	}
+
+	// the body includes the likes of the user, so it must not be shared with others
+	Ok(HttpResponse::Ok()
+		.insert_header((header::ETAG, etag))
+		.insert_header((header::CACHE_CONTROL, "private, no-cache"))
+		.content_type(ContentType::json())
+		.body(body))
+}
+
+#[get("/names/{name}")]
+pub async fn view_name(
+	name: web::Path<String>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let name = name.into_inner().to_ascii_lowercase();
+
+	let ((details, liked), snipe) = pool::run(&pool, move |connection| {
+		let details = schema::name::table
+			.left_join(
+				schema::like::table.on(schema::like::username
+					.eq(schema::name::username)
+					.and(schema::like::user_id.eq(user.id))),
+			)
+			.filter(schema::name::username.eq(&name))
+			.select((Name::as_select(), liked()))
+			.get_result::<(Name, bool)>(connection)
+			.optional()?
+			.ok_or(Error::NotFound)?;
+
+		let snipe = schema::snipe::table
+			.select(FormattedSnipe::as_select())
+			.filter(schema::snipe::username.eq(&name))
+			.filter(schema::snipe::user_id.eq(user.id))
+			.get_result::<FormattedSnipe>(connection)
+			.optional()?;
+
+		Ok((details, snipe))
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(NameDetailsResponse {
+		name: details,
+		liked,
+		snipe,
 	}))
 }
 
-#[get("/names/{name}/dislike")]
-pub async fn dislike_name(
+#[get("/names/{name}/history")]
+pub async fn view_name_history(
 	name: web::Path<String>,
-	req: HttpRequest,
+	options: web::Query<ViewNameHistoryOptions>,
+	_user: User,
 	pool: web::Data<PostgresPool>,
-) -> Result<HttpResponse, actix_web::Error> {
-	let token = req
-		.headers()
-		.get(header::AUTHORIZATION)
-		.ok_or(actix_web::error::ErrorUnauthorized(""))?
-		.to_str()
-		.map_err(|_| actix_web::error::ErrorUnauthorized(""))?;
-
-	let connection = &mut pool
-		.get()
-		.map_err(|_| actix_web::error::ErrorInternalServerError(""))?;
-
-	let user_id = schema::user::table
-		.select(schema::user::id)
-		.filter(schema::user::key.eq(token))
-		.get_result::<i32>(connection);
-
-	let user_id = match user_id {
-		Ok(user_id) => user_id,
-		Err(_) => return Err(actix_web::error::ErrorUnauthorized("")),
-	};
+) -> Result<HttpResponse, Error> {
+	let name = name.into_inner().to_ascii_lowercase();
+	let options = options.into_inner();
+
+	let events = pool::run(&pool, move |connection| {
+		let exists = diesel::select(diesel::dsl::exists(
+			schema::name::table.filter(schema::name::username.eq(&name)),
+		))
+		.get_result::<bool>(connection)?;
+
+		if !exists {
+			return Err(Error::NotFound);
+		}
+
+		// ids increase with `created_at`, so they double as the cursor
+		let mut events = schema::name_event::table
+			.select(NameEvent::as_select())
+			.filter(schema::name_event::username.eq(&name))
+			.order(schema::name_event::id.asc())
+			.limit(options.limit.unwrap_or(100).clamp(0, MAX_HISTORY_LIMIT))
+			.into_boxed();
+
+		if let Some(after) = options.after {
+			events = events.filter(schema::name_event::id.gt(after));
+		}
+
+		Ok(events.load::<NameEvent>(connection)?)
+	})
+	.await?;
 
-	let updates = diesel::delete(schema::like::table)
-		.filter(
-			schema::like::username
-				.eq(name.into_inner())
-				.and(schema::like::user_id.eq(user_id)),
-		)
-		.execute(connection)
-		.map_err(|_| actix_web::error::ErrorInternalServerError(""))?;
-
-	Ok(HttpResponse::Ok().json(NameResponse {
-		updated: updates > 0,
+	Ok(HttpResponse::Ok().json(NameHistoryResponse {
+		data: events.into_iter().map(FormattedNameEvent::from).collect(),
 	}))
 }
@@ -0,0 +1,260 @@
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use database::{
+	models::{NewProxy, Proxy},
+	proxies, schema, PostgresPool,
+};
+use diesel::{
+	Connection, ExpressionMethods, OptionalExtension, QueryDsl, Queryable, RunQueryDsl, Selectable,
+	SelectableHelper,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{auth::Admin, error::Error, pool};
+
+/// Distinguishes a field that was set to `null` from one that was omitted.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: Deserialize<'de>,
+{
+	T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize)]
+pub struct CreateProxyOptions {
+	pub address: String,
+	pub port: i32,
+	pub username: Option<String>,
+	pub password: Option<String>,
+	pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateProxyOptions {
+	pub address: Option<String>,
+	pub port: Option<i32>,
+	#[serde(default, deserialize_with = "present")]
+	pub username: Option<Option<String>>,
+	#[serde(default, deserialize_with = "present")]
+	pub password: Option<Option<String>>,
+	#[serde(default, deserialize_with = "present")]
+	pub note: Option<Option<String>>,
+}
+
+/// A proxy without its password.
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(table_name = schema::proxy, check_for_backend(diesel::pg::Pg))]
+pub struct FormattedProxy {
+	pub id: i32,
+	pub address: String,
+	pub port: i32,
+	pub username: Option<String>,
+	pub note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ViewProxiesResponse {
+	pub data: Vec<FormattedProxy>,
+}
+
+#[derive(Serialize)]
+pub struct ProxyResponse {
+	pub updated: bool,
+}
+
+#[derive(Serialize)]
+pub struct ImportError {
+	/// The 1-based line number in the request body
+	pub line: usize,
+	pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportProxiesResponse {
+	/// The number of proxies that were added
+	pub created: usize,
+	/// The lines that could not be parsed, which were skipped
+	pub errors: Vec<ImportError>,
+}
+
+impl From<Proxy> for FormattedProxy {
+	fn from(proxy: Proxy) -> Self {
+		Self {
+			id: proxy.id,
+			address: proxy.address,
+			port: proxy.port,
+			username: proxy.username,
+			note: proxy.note,
+		}
+	}
+}
+
+#[get("/admin/proxies")]
+pub async fn view_proxies(
+	_admin: Admin,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let proxies = pool::run(&pool, |connection| {
+		Ok(schema::proxy::table
+			.select(FormattedProxy::as_select())
+			.order(schema::proxy::id.asc())
+			.load(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(ViewProxiesResponse { data: proxies }))
+}
+
+#[post("/admin/proxies")]
+pub async fn create_proxy(
+	data: web::Json<CreateProxyOptions>,
+	_admin: Admin,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	proxies::validate(
+		&data.address,
+		data.port,
+		data.username.as_deref(),
+		data.password.as_deref(),
+	)?;
+
+	let data = data.into_inner();
+
+	let proxy = pool::run(&pool, move |connection| {
+		Ok(diesel::insert_into(schema::proxy::table)
+			.values(NewProxy {
+				address: &data.address,
+				port: data.port,
+				username: data.username.as_deref(),
+				password: data.password.as_deref(),
+				note: data.note.as_deref(),
+			})
+			.get_result::<Proxy>(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Created().json(FormattedProxy::from(proxy)))
+}
+
+#[post("/admin/proxies/import")]
+pub async fn import_proxies(
+	body: String,
+	_admin: Admin,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	// the proxies borrow from the body, so they are parsed where they are inserted
+	let (created, errors) = pool::run(&pool, move |connection| {
+		let mut valid = Vec::new();
+		let mut errors = Vec::new();
+
+		for (index, line) in body.lines().enumerate() {
+			let line = line.trim();
+
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			match proxies::parse(line) {
+				Ok(proxy) => valid.push(proxy),
+				Err(e) => errors.push(ImportError {
+					line: index + 1,
+					message: e.to_string(),
+				}),
+			}
+		}
+
+		let created = diesel::insert_into(schema::proxy::table)
+			.values(&valid)
+			.execute(connection)?;
+
+		Ok((created, errors))
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(ImportProxiesResponse { created, errors }))
+}
+
+#[patch("/admin/proxies/{id}")]
+pub async fn update_proxy(
+	id: web::Path<i32>,
+	data: web::Json<UpdateProxyOptions>,
+	_admin: Admin,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let id = id.into_inner();
+	let data = data.into_inner();
+
+	// the changes are validated against the stored proxy, since credentials depend on each other
+	let proxy = pool::run(&pool, move |connection| {
+		Ok(connection.transaction(|connection| {
+			let Some(mut proxy) = schema::proxy::table
+				.find(id)
+				.for_update()
+				.get_result::<Proxy>(connection)
+				.optional()?
+			else {
+				return Ok(None);
+			};
+
+			if let Some(address) = data.address {
+				proxy.address = address;
+			}
+
+			if let Some(port) = data.port {
+				proxy.port = port;
+			}
+
+			if let Some(username) = data.username {
+				proxy.username = username;
+			}
+
+			if let Some(password) = data.password {
+				proxy.password = password;
+			}
+
+			if let Some(note) = data.note {
+				proxy.note = note;
+			}
+
+			if let Err(e) = proxies::validate(
+				&proxy.address,
+				proxy.port,
+				proxy.username.as_deref(),
+				proxy.password.as_deref(),
+			) {
+				return Ok(Some(Err(e)));
+			}
+
+			diesel::update(&proxy).set(&proxy).execute(connection)?;
+
+			Ok::<_, diesel::result::Error>(Some(Ok(proxy)))
+		})?)
+	})
+	.await?
+	.ok_or(Error::NotFound)?
+	.map_err(Error::from)?;
+
+	Ok(HttpResponse::Ok().json(FormattedProxy::from(proxy)))
+}
+
+#[delete("/admin/proxies/{id}")]
+pub async fn delete_proxy(
+	id: web::Path<i32>,
+	_admin: Admin,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let id = id.into_inner();
+
+	let updates = pool::run(&pool, move |connection| {
+		Ok(diesel::delete(schema::proxy::table)
+			.filter(schema::proxy::id.eq(id))
+			.execute(connection)?)
+	})
+	.await?;
+
+	if updates == 0 {
+		return Err(Error::NotFound);
+	}
+
+	Ok(HttpResponse::Ok().json(ProxyResponse { updated: true }))
+}
@@ -0,0 +1,199 @@
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use database::{
+	filter::NameFilter,
+	models::{NewSavedSearch, SavedSearch, SavedSearchChanges},
+	schema, PostgresPool,
+};
+use diesel::{ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use crate::{auth::User, error::Error, handlers::names::check_pattern, pool};
+
+#[derive(Deserialize)]
+pub struct CreateSearchOptions {
+	pub name: String,
+	pub filter: NameFilter,
+	#[serde(default = "default_alerts")]
+	pub alerts: bool,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateSearchOptions {
+	pub name: Option<String>,
+	pub filter: Option<NameFilter>,
+	pub alerts: Option<bool>,
+}
+
+fn default_alerts() -> bool {
+	true
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedSearch {
+	pub id: i32,
+	pub name: String,
+	pub filter: serde_json::Value,
+	/// Whether names that become available and match the filter create an alert
+	pub alerts: bool,
+	pub created_at: chrono::DateTime<chrono::Utc>,
+	pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ViewSearchesResponse {
+	pub data: Vec<FormattedSearch>,
+}
+
+#[derive(Serialize)]
+pub struct SearchResponse {
+	pub updated: bool,
+}
+
+impl From<SavedSearch> for FormattedSearch {
+	fn from(search: SavedSearch) -> Self {
+		Self {
+			id: search.id,
+			name: search.name,
+			filter: search.filter,
+			alerts: search.alerts,
+			created_at: search.created_at,
+			updated_at: search.updated_at,
+		}
+	}
+}
+
+/// Validates a filter before it is saved, returning it in the form it is stored in.
+fn prepare_filter(
+	filter: &NameFilter,
+	connection: &mut PgConnection,
+) -> Result<serde_json::Value, Error> {
+	filter.validate()?;
+	check_pattern(filter, connection)?;
+
+	Ok(serde_json::to_value(filter).expect("filter to always be serializable"))
+}
+
+#[get("/searches")]
+pub async fn view_searches(
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let searches = pool::run(&pool, move |connection| {
+		Ok(schema::saved_search::table
+			.filter(schema::saved_search::user_id.eq(user.id))
+			.order(schema::saved_search::created_at.desc())
+			.load::<SavedSearch>(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(ViewSearchesResponse {
+		data: searches.into_iter().map(FormattedSearch::from).collect(),
+	}))
+}
+
+#[post("/searches")]
+pub async fn create_search(
+	data: web::Json<CreateSearchOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let data = data.into_inner();
+
+	let search = pool::run(&pool, move |connection| {
+		let filter = prepare_filter(&data.filter, connection)?;
+
+		Ok(diesel::insert_into(schema::saved_search::table)
+			.values(NewSavedSearch {
+				user_id: user.id,
+				name: &data.name,
+				filter,
+				alerts: data.alerts,
+			})
+			.get_result::<SavedSearch>(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Created().json(FormattedSearch::from(search)))
+}
+
+#[get("/searches/{id}")]
+pub async fn view_search(
+	id: web::Path<i32>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let id = id.into_inner();
+
+	let search = pool::run(&pool, move |connection| {
+		schema::saved_search::table
+			.filter(schema::saved_search::id.eq(id))
+			.filter(schema::saved_search::user_id.eq(user.id))
+			.get_result::<SavedSearch>(connection)
+			.optional()?
+			.ok_or(Error::NotFound)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(FormattedSearch::from(search)))
+}
+
+#[patch("/searches/{id}")]
+pub async fn update_search(
+	id: web::Path<i32>,
+	data: web::Json<UpdateSearchOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let id = id.into_inner();
+	let data = data.into_inner();
+
+	let search = pool::run(&pool, move |connection| {
+		let filter = data
+			.filter
+			.as_ref()
+			.map(|filter| prepare_filter(filter, connection))
+			.transpose()?;
+
+		diesel::update(schema::saved_search::table)
+			.filter(schema::saved_search::id.eq(id))
+			.filter(schema::saved_search::user_id.eq(user.id))
+			.set((
+				SavedSearchChanges {
+					name: data.name.as_deref(),
+					filter,
+					alerts: data.alerts,
+				},
+				schema::saved_search::updated_at.eq(diesel::dsl::now),
+			))
+			.get_result::<SavedSearch>(connection)
+			.optional()?
+			.ok_or(Error::NotFound)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(FormattedSearch::from(search)))
+}
+
+#[delete("/searches/{id}")]
+pub async fn delete_search(
+	id: web::Path<i32>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let id = id.into_inner();
+
+	let updates = pool::run(&pool, move |connection| {
+		Ok(diesel::delete(schema::saved_search::table)
+			.filter(schema::saved_search::id.eq(id))
+			.filter(schema::saved_search::user_id.eq(user.id))
+			.execute(connection)?)
+	})
+	.await?;
+
+	if updates == 0 {
+		return Err(Error::NotFound);
+	}
+
+	Ok(HttpResponse::Ok().json(SearchResponse { updated: true }))
+}
@@ -1,8 +1,17 @@
-use actix_web::{http::header, post, web, HttpRequest, HttpResponse};
-use database::{schema, PostgresPool};
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use database::{crypto::Secret, models::NewSnipe, schema, PostgresPool};
+use diesel::{
+	ExpressionMethods, OptionalExtension, QueryDsl, Queryable, RunQueryDsl, Selectable,
+	SelectableHelper,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+	auth::User,
+	error::{invalid, Error},
+	pool,
+};
+
 #[derive(Deserialize)]
 pub struct CreateSnipeOptions {
 	pub username: String,
@@ -11,48 +20,156 @@ pub struct CreateSnipeOptions {
 	pub workers: i16,
 }
 
+#[derive(Deserialize)]
+pub struct UpdateSnipeOptions {
+	pub workers: i16,
+}
+
+/// A snipe without the credentials of the account it will be claimed with.
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(table_name = schema::snipe, check_for_backend(diesel::pg::Pg))]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedSnipe {
+	pub username: String,
+	/// The number of workers requested
+	pub needed: i16,
+	/// The number of workers currently checking the name
+	pub count: i16,
+	pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Serialize)]
 pub struct CreateSnipeResponse {
 	pub updated: bool,
 }
 
+#[derive(Serialize)]
+pub struct ViewSnipesResponse {
+	pub data: Vec<FormattedSnipe>,
+}
+
 #[post("/snipe")]
 pub async fn create_snipe(
 	data: web::Json<CreateSnipeOptions>,
-	req: HttpRequest,
+	user: User,
 	pool: web::Data<PostgresPool>,
-) -> Result<HttpResponse, actix_web::Error> {
-	// get the token from the "Authorization" header, or return a 401 if it does not exist or cannot be parsed
-	let token = req
-		.headers()
-		.get(header::AUTHORIZATION)
-		.ok_or(actix_web::error::ErrorUnauthorized(""))?
-		.to_str()
-		.map_err(|_| actix_web::error::ErrorUnauthorized(""))?;
-
-	let connection = &mut pool
-		.get()
-		.map_err(|_| actix_web::error::ErrorInternalServerError(""))?;
-
-	let _user_id = schema::user::table
-		.select(schema::user::id)
-		.filter(schema::user::key.eq(token))
-		.get_result::<i32>(connection)
-		.map_err(|_| actix_web::error::ErrorUnauthorized(""))?;
+	secret: web::Data<Secret>,
+) -> Result<HttpResponse, Error> {
+	if data.workers < 1 {
+		return Err(invalid("workers", "must be at least 1"));
+	}
+
+	let snipe = NewSnipe {
+		username: data.username.to_ascii_lowercase(),
+		needed: data.workers,
+		count: 0,
+		email: secret.encrypt(&data.email),
+		password: secret.encrypt(&data.password),
+		user_id: Some(user.id),
+	};
 
 	// add the snipe to the database
-	let updates = diesel::insert_into(schema::snipe::table)
-		.values((
-			schema::snipe::username.eq(&data.username),
-			schema::snipe::needed.eq(&data.workers),
-			schema::snipe::email.eq(&data.email),
-			schema::snipe::password.eq(&data.password),
-		))
-		.on_conflict_do_nothing()
-		.execute(connection)
-		.map_err(|_| actix_web::error::ErrorInternalServerError(""))?;
-
-	Ok(HttpResponse::Ok().json(CreateSnipeResponse {
-		updated: updates > 0,
-	}))
+	let updates = pool::run(&pool, move |connection| {
+		Ok(diesel::insert_into(schema::snipe::table)
+			.values(snipe)
+			.on_conflict_do_nothing()
+			.execute(connection)?)
+	})
+	.await?;
+
+	// the name is already being sniped, possibly by someone else
+	if updates == 0 {
+		return Err(Error::Conflict("the name is already being sniped"));
+	}
+
+	Ok(HttpResponse::Ok().json(CreateSnipeResponse { updated: true }))
+}
+
+#[get("/snipes")]
+pub async fn view_snipes(user: User, pool: web::Data<PostgresPool>) -> Result<HttpResponse, Error> {
+	let snipes = pool::run(&pool, move |connection| {
+		Ok(schema::snipe::table
+			.select(FormattedSnipe::as_select())
+			.filter(schema::snipe::user_id.eq(user.id))
+			.order(schema::snipe::created_at.desc())
+			.load(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(ViewSnipesResponse { data: snipes }))
+}
+
+#[get("/snipe/{username}")]
+pub async fn view_snipe(
+	username: web::Path<String>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let username = username.to_ascii_lowercase();
+
+	let snipe = pool::run(&pool, move |connection| {
+		schema::snipe::table
+			.select(FormattedSnipe::as_select())
+			.filter(schema::snipe::username.eq(username))
+			.filter(schema::snipe::user_id.eq(user.id))
+			.get_result(connection)
+			.optional()?
+			.ok_or(Error::NotFound)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(snipe))
+}
+
+#[patch("/snipe/{username}")]
+pub async fn update_snipe(
+	username: web::Path<String>,
+	data: web::Json<UpdateSnipeOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	if data.workers < 1 {
+		return Err(invalid("workers", "must be at least 1"));
+	}
+
+	let username = username.to_ascii_lowercase();
+	let workers = data.workers;
+
+	// workers holding a slot beyond the new size release it the next time they refresh the snipe
+	let snipe = pool::run(&pool, move |connection| {
+		diesel::update(schema::snipe::table)
+			.filter(schema::snipe::username.eq(username))
+			.filter(schema::snipe::user_id.eq(user.id))
+			.set(schema::snipe::needed.eq(workers))
+			.returning(FormattedSnipe::as_returning())
+			.get_result(connection)
+			.optional()?
+			.ok_or(Error::NotFound)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(snipe))
+}
+
+#[delete("/snipe/{username}")]
+pub async fn delete_snipe(
+	username: web::Path<String>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let username = username.to_ascii_lowercase();
+
+	let updates = pool::run(&pool, move |connection| {
+		Ok(diesel::delete(schema::snipe::table)
+			.filter(schema::snipe::username.eq(username))
+			.filter(schema::snipe::user_id.eq(user.id))
+			.execute(connection)?)
+	})
+	.await?;
+
+	if updates == 0 {
+		return Err(Error::NotFound);
+	}
+
+	Ok(HttpResponse::Ok().json(CreateSnipeResponse { updated: true }))
 }
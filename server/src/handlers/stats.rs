@@ -0,0 +1,282 @@
+use std::sync::{Arc, RwLock};
+
+use actix_web::{get, web, HttpResponse};
+use database::{schema, tiers, PostgresPool, Status};
+use diesel::{
+	sql_types::{BigInt, Date, Double, Int2, Integer, Text, Timestamptz},
+	ExpressionMethods, PgConnection, QueryDsl, QueryResult, QueryableByName, RunQueryDsl,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	auth::User,
+	error::{invalid, Error},
+	pool,
+};
+
+/// The most days of availability history returned
+const MAX_DAYS: i32 = 90;
+/// How long computed statistics are served before they are recomputed
+const MAX_AGE_SECONDS: i64 = 60;
+
+#[derive(Deserialize)]
+pub struct ViewStatsOptions {
+	/// The number of days of availability history to return, including today
+	pub days: Option<i32>,
+}
+
+#[derive(Serialize, Clone, Copy)]
+pub struct StatusCount {
+	pub status: Status,
+	pub count: i64,
+}
+
+#[derive(QueryableByName, Serialize)]
+pub struct TagCount {
+	#[diesel(sql_type = Text)]
+	pub tag: String,
+	#[diesel(sql_type = BigInt)]
+	pub count: i64,
+}
+
+#[derive(Serialize)]
+pub struct LengthCount {
+	pub length: i32,
+	pub count: i64,
+}
+
+#[derive(QueryableByName, Serialize)]
+pub struct DayCount {
+	#[diesel(sql_type = Date)]
+	pub date: chrono::NaiveDate,
+	#[diesel(sql_type = BigInt)]
+	pub count: i64,
+}
+
+/// The number of names whose latest check happened within an hour.
+///
+/// Only the latest check of each name is stored, so a name checked several times
+/// in the same hour is only counted once.
+#[derive(QueryableByName, Serialize)]
+pub struct HourChecks {
+	#[diesel(sql_type = Timestamptz)]
+	pub hour: chrono::DateTime<chrono::Utc>,
+	#[diesel(sql_type = BigInt)]
+	pub batcher: i64,
+	#[diesel(sql_type = BigInt)]
+	pub checker: i64,
+}
+
+#[derive(QueryableByName, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TierStaleness {
+	#[diesel(sql_type = Text)]
+	pub tier: String,
+	#[diesel(sql_type = BigInt)]
+	pub count: i64,
+	/// The median number of seconds since a name in the tier was last checked
+	#[diesel(sql_type = Double)]
+	pub median_staleness: f64,
+}
+
+pub struct Stats {
+	pub total: i64,
+	pub statuses: Vec<StatusCount>,
+	pub tags: Vec<TagCount>,
+	pub lengths: Vec<LengthCount>,
+	/// The number of names that became available each day, oldest first
+	pub available: Vec<DayCount>,
+	/// The checks made each hour over the last day, oldest first
+	pub checks: Vec<HourChecks>,
+	pub tiers: Vec<TierStaleness>,
+	pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewStatsResponse<'a> {
+	pub total: i64,
+	pub statuses: &'a [StatusCount],
+	pub tags: &'a [TagCount],
+	pub lengths: &'a [LengthCount],
+	pub available: &'a [DayCount],
+	pub checks: &'a [HourChecks],
+	pub tiers: &'a [TierStaleness],
+	pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The most recently computed statistics, shared between workers.
+#[derive(Default)]
+pub struct StatsCache {
+	stats: RwLock<Option<Arc<Stats>>>,
+	/// Held by the only request recomputing the statistics
+	refresh: tokio::sync::Mutex<()>,
+}
+
+impl StatsCache {
+	fn cached(&self) -> Option<Arc<Stats>> {
+		self.stats
+			.read()
+			.expect("stats lock to not be poisoned")
+			.clone()
+	}
+
+	fn is_fresh(stats: &Stats) -> bool {
+		(chrono::Utc::now() - stats.generated_at).num_seconds() < MAX_AGE_SECONDS
+	}
+
+	/// Returns the cached statistics, recomputing them if they are too old.
+	///
+	/// Only one request recomputes them at a time. The others are served the old
+	/// statistics, or wait for the result without holding a connection if there are none.
+	async fn get(&self, pool: &PostgresPool) -> Result<Arc<Stats>, Error> {
+		let stale = match self.cached() {
+			Some(stats) if Self::is_fresh(&stats) => return Ok(stats),
+			stats => stats,
+		};
+
+		let _refresh = match (self.refresh.try_lock(), stale) {
+			(Ok(refresh), _) => refresh,
+			(Err(_), Some(stale)) => return Ok(stale),
+			(Err(_), None) => self.refresh.lock().await,
+		};
+
+		// another request may have finished recomputing while this one waited
+		if let Some(stats) = self.cached().filter(|stats| Self::is_fresh(stats)) {
+			return Ok(stats);
+		}
+
+		// the statistics cover every name, so they can take longer than a request is allowed to
+		let stats = Arc::new(
+			pool::run(pool, |connection| {
+				Ok(database::without_statement_timeout(connection, compute)?)
+			})
+			.await?,
+		);
+
+		*self.stats.write().expect("stats lock to not be poisoned") = Some(stats.clone());
+
+		Ok(stats)
+	}
+}
+
+fn compute(connection: &mut PgConnection) -> QueryResult<Stats> {
+	let statuses = schema::name::table
+		.group_by(schema::name::status)
+		.select((schema::name::status, diesel::dsl::count_star()))
+		.order(schema::name::status.asc())
+		.load::<(i16, i64)>(connection)?
+		.into_iter()
+		.map(|(status, count)| StatusCount {
+			status: status.into(),
+			count,
+		})
+		.collect::<Vec<_>>();
+
+	let tags = diesel::sql_query(
+		"SELECT tag, COUNT(*) AS count FROM name, unnest(tags) AS tag \
+		 GROUP BY tag ORDER BY count DESC, tag",
+	)
+	.load::<TagCount>(connection)?;
+
+	let lengths = schema::name::table
+		.group_by(schema::name::length)
+		.select((schema::name::length, diesel::dsl::count_star()))
+		.order(schema::name::length.asc())
+		.load::<(i32, i64)>(connection)?
+		.into_iter()
+		.map(|(length, count)| LengthCount { length, count })
+		.collect();
+
+	// every day is included, even if no names became available on it
+	let available = diesel::sql_query(
+		"SELECT day::date AS date, COUNT(name_event.id) AS count \
+		 FROM generate_series(date_trunc('day', now()) - make_interval(days => $1 - 1), \
+		 date_trunc('day', now()), interval '1 day') AS day \
+		 LEFT JOIN name_event ON name_event.new_status = $2 \
+		 AND name_event.created_at >= day AND name_event.created_at < day + interval '1 day' \
+		 GROUP BY day ORDER BY day",
+	)
+	.bind::<Integer, _>(MAX_DAYS)
+	.bind::<Int2, _>(i16::from(Status::Available))
+	.load::<DayCount>(connection)?;
+
+	// the batcher records its checks in `checked_at` and the checker in `verified_at`
+	let checks = diesel::sql_query(
+		"WITH checks AS ( \
+		 SELECT date_trunc('hour', checked_at) AS hour, 1 AS batcher, 0 AS checker FROM name \
+		 WHERE checked_at >= date_trunc('hour', now()) - interval '23 hours' \
+		 UNION ALL \
+		 SELECT date_trunc('hour', verified_at), 0, 1 FROM name \
+		 WHERE verified_at >= date_trunc('hour', now()) - interval '23 hours') \
+		 SELECT hour, COALESCE(SUM(batcher), 0)::int8 AS batcher, \
+		 COALESCE(SUM(checker), 0)::int8 AS checker \
+		 FROM generate_series(date_trunc('hour', now()) - interval '23 hours', \
+		 date_trunc('hour', now()), interval '1 hour') AS hour \
+		 LEFT JOIN checks USING (hour) GROUP BY hour ORDER BY hour",
+	)
+	.load::<HourChecks>(connection)?;
+
+	// mirrors the filters the checker uses to pick names from each tier
+	let tiers = diesel::sql_query(
+		"SELECT tier, COUNT(*) AS count, percentile_cont(0.5) WITHIN GROUP \
+		 (ORDER BY EXTRACT(EPOCH FROM now() - verified_at)::float8) AS median_staleness \
+		 FROM (SELECT verified_at, CASE \
+		 WHEN frequency >= $1 THEN 'high' \
+		 WHEN status = $4 THEN NULL \
+		 WHEN frequency >= $2 THEN 'medium' \
+		 WHEN frequency >= $3 OR definition IS NOT NULL THEN 'low' \
+		 END AS tier FROM name) AS tiers \
+		 WHERE tier IS NOT NULL GROUP BY tier \
+		 ORDER BY array_position(ARRAY['high', 'medium', 'low'], tier)",
+	)
+	.bind::<Double, _>(tiers::HIGH)
+	.bind::<Double, _>(tiers::MEDIUM)
+	.bind::<Double, _>(tiers::LOW)
+	.bind::<Int2, _>(i16::from(Status::BatchTaken))
+	.load::<TierStaleness>(connection)?;
+
+	Ok(Stats {
+		total: statuses.iter().map(|status| status.count).sum(),
+		statuses,
+		tags,
+		lengths,
+		available,
+		checks,
+		tiers,
+		generated_at: chrono::Utc::now(),
+	})
+}
+
+#[get("/stats")]
+pub async fn view_stats(
+	options: web::Query<ViewStatsOptions>,
+	_user: User,
+	pool: web::Data<PostgresPool>,
+	cache: web::Data<StatsCache>,
+) -> Result<HttpResponse, Error> {
+	let days = options.days.unwrap_or(30);
+
+	if !(1..=MAX_DAYS).contains(&days) {
+		return Err(invalid("days", "must be between 1 and 90"));
+	}
+
+	let stats = cache.get(&pool).await?;
+
+	// the history is always computed for the most days, so any range can be served from it
+	let available = &stats.available[stats
+		.available
+		.len()
+		.saturating_sub(days.unsigned_abs() as usize)..];
+
+	Ok(HttpResponse::Ok().json(ViewStatsResponse {
+		total: stats.total,
+		statuses: &stats.statuses,
+		tags: &stats.tags,
+		lengths: &stats.lengths,
+		available,
+		checks: &stats.checks,
+		tiers: &stats.tiers,
+		generated_at: stats.generated_at,
+	}))
+}
@@ -0,0 +1,301 @@
+use std::time::Duration;
+
+use actix_web::{get, http::header, web, HttpResponse};
+use database::{
+	channels,
+	filter::{NameFilter, Range},
+	models::{Alert, Name},
+	schema, PostgresPool,
+};
+use diesel::{connection::SimpleConnection, prelude::*};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+use crate::{
+	auth::User,
+	error::Error,
+	handlers::{
+		alerts::FormattedAlert,
+		names::{FormattedName, NameCache},
+	},
+	pool,
+};
+
+/// How often the listener checks its connection for new notifications
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How often an idle stream sends a comment to keep the connection open
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+pub type NameSender = broadcast::Sender<FormattedName>;
+pub type AlertSender = broadcast::Sender<FormattedAlert>;
+
+/// The filters of `POST /names` that can be checked without the database, as query
+/// parameters. Any other filter is rejected rather than ignored, so a stream never
+/// includes names the listing would not.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StreamNamesOptions {
+	pub search: Option<String>,
+	/// A comma-separated list of tags, with the same meaning as in `POST /names`
+	pub tags: Option<String>,
+	/// A comma-separated list of tags that every name must have
+	pub include_tags: Option<String>,
+	/// A comma-separated list of tags that no name can have
+	pub exclude_tags: Option<String>,
+	pub min_length: Option<i32>,
+	pub max_length: Option<i32>,
+	pub min_frequency: Option<f64>,
+	pub max_frequency: Option<f64>,
+	pub min_popularity: Option<f64>,
+	pub max_popularity: Option<f64>,
+}
+
+impl From<StreamNamesOptions> for NameFilter {
+	fn from(options: StreamNamesOptions) -> Self {
+		fn list(list: Option<String>) -> Option<Vec<String>> {
+			list.map(|list| {
+				list.split(',')
+					.filter(|item| !item.is_empty())
+					.map(str::to_string)
+					.collect()
+			})
+		}
+
+		fn range<T>(min: Option<T>, max: Option<T>) -> Option<Range<T>> {
+			(min.is_some() || max.is_some()).then_some(Range { min, max })
+		}
+
+		NameFilter {
+			search: options.search,
+			tags: list(options.tags),
+			include_tags: list(options.include_tags),
+			exclude_tags: list(options.exclude_tags),
+			length: range(options.min_length, options.max_length),
+			frequency: range(options.min_frequency, options.max_frequency),
+			popularity: range(options.min_popularity, options.max_popularity),
+			..NameFilter::default()
+		}
+	}
+}
+
+/// Loads the name a notification on [`channels::NAME_STATUS`] refers to and sends it to every open stream.
+fn broadcast_name(pool: &PostgresPool, sender: &NameSender, username: &str) {
+	// nobody is listening, so there is no need to load the name
+	if sender.receiver_count() == 0 {
+		return;
+	}
+
+	let Ok(mut connection) = pool.get() else {
+		return;
+	};
+
+	let name = schema::name::table
+		.filter(schema::name::username.eq(username))
+		.select(Name::as_select())
+		.get_result(&mut connection);
+
+	if let Ok(name) = name {
+		sender
+			.send(FormattedName {
+				name,
+				liked: None,
+				rank: None,
+			})
+			.ok();
+	}
+}
+
+/// Loads the alert a notification on [`channels::ALERT`] refers to and sends it to every open stream.
+fn broadcast_alert(pool: &PostgresPool, sender: &AlertSender, id: &str) {
+	if sender.receiver_count() == 0 {
+		return;
+	}
+
+	let (Ok(id), Ok(mut connection)) = (id.parse::<i64>(), pool.get()) else {
+		return;
+	};
+
+	let alert = schema::alert::table
+		.left_join(schema::saved_search::table)
+		.filter(schema::alert::id.eq(id))
+		.select((Alert::as_select(), schema::saved_search::name.nullable()))
+		.get_result::<(Alert, Option<String>)>(&mut connection);
+
+	if let Ok(alert) = alert {
+		sender.send(FormattedAlert::from(alert)).ok();
+	}
+}
+
+/// Listens for names changing status, and for new alerts, and broadcasts them to every
+/// open stream. Cached listings are dropped whenever a name changes status.
+///
+/// This blocks forever, so it should be run on its own thread.
+pub fn listen(
+	pool: PostgresPool,
+	names: NameSender,
+	alerts: AlertSender,
+	cache: web::Data<NameCache>,
+) {
+	let url = std::env::var("DATABASE_URL").expect("environment variable DATABASE_URL not found");
+
+	loop {
+		let mut connection = match PgConnection::establish(&url) {
+			Ok(connection) => connection,
+			Err(e) => {
+				eprintln!("could not connect to listen for name updates: {e}");
+				std::thread::sleep(Duration::from_secs(5));
+				continue;
+			}
+		};
+
+		if let Err(e) = connection.batch_execute(&format!(
+			"LISTEN {}; LISTEN {};",
+			channels::NAME_STATUS,
+			channels::ALERT
+		)) {
+			eprintln!("could not listen for name updates: {e}");
+			std::thread::sleep(Duration::from_secs(5));
+			continue;
+		}
+
+		// changes made while reconnecting were missed, so nothing cached before them can be trusted
+		cache.clear();
+
+		'poll: loop {
+			for notification in connection.notifications_iter() {
+				let Ok(notification) = notification else {
+					// the connection was lost, so reconnect
+					break 'poll;
+				};
+
+				match notification.channel.as_str() {
+					channels::NAME_STATUS => {
+						cache.clear();
+						broadcast_name(&pool, &names, &notification.payload);
+					}
+					channels::ALERT => broadcast_alert(&pool, &alerts, &notification.payload),
+					_ => {}
+				}
+			}
+
+			std::thread::sleep(POLL_INTERVAL);
+		}
+	}
+}
+
+/// Streams the names matching the filters as they change status. Whether a name is
+/// liked is only included when filtering by the `liked` tag.
+#[get("/stream/names")]
+pub async fn stream_names(
+	options: web::Query<StreamNamesOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+	sender: web::Data<NameSender>,
+) -> Result<HttpResponse, Error> {
+	let filter = NameFilter::from(options.into_inner());
+
+	filter.validate()?;
+
+	let user_id = user.id;
+	let state = (
+		sender.subscribe(),
+		tokio::time::interval(KEEP_ALIVE_INTERVAL),
+		filter,
+		pool.into_inner(),
+	);
+
+	let stream = futures::stream::unfold(
+		state,
+		move |(mut receiver, mut keep_alive, filter, pool)| async move {
+			let event = loop {
+				let mut name = tokio::select! {
+					name = receiver.recv() => match name {
+						Ok(name) => name,
+						// some names were missed because this stream fell behind
+						Err(broadcast::error::RecvError::Lagged(_)) => continue,
+						Err(broadcast::error::RecvError::Closed) => return None,
+					},
+					_ = keep_alive.tick() => break web::Bytes::from_static(b": keep-alive\n\n"),
+				};
+
+				if !filter.matches(&name.name, None) {
+					continue;
+				}
+
+				// only streams filtering for liked names look them up, since every stream
+				// would otherwise query the database for every name that changes status
+				if filter.is_personal() {
+					let username = name.name.username.clone();
+					let liked = pool::run(&pool, move |connection| {
+						Ok(diesel::select(diesel::dsl::exists(
+							schema::like::table
+								.filter(schema::like::username.eq(username))
+								.filter(schema::like::user_id.eq(user_id)),
+						))
+						.get_result::<bool>(connection)?)
+					})
+					.await;
+
+					if !liked.unwrap_or(false) {
+						continue;
+					}
+
+					name.liked = Some(true);
+				}
+
+				let data = serde_json::to_string(&name).expect("name to always be serializable");
+
+				break web::Bytes::from(format!("event: name\ndata: {data}\n\n"));
+			};
+
+			Some((
+				Ok::<_, actix_web::Error>(event),
+				(receiver, keep_alive, filter, pool),
+			))
+		},
+	);
+
+	Ok(HttpResponse::Ok()
+		.content_type("text/event-stream")
+		.insert_header((header::CACHE_CONTROL, "no-cache"))
+		.streaming(stream))
+}
+
+/// Streams the alerts created for the current user as they happen.
+#[get("/stream/alerts")]
+pub async fn stream_alerts(
+	user: User,
+	sender: web::Data<AlertSender>,
+) -> Result<HttpResponse, Error> {
+	let user_id = user.id;
+	let state = (
+		sender.subscribe(),
+		tokio::time::interval(KEEP_ALIVE_INTERVAL),
+	);
+
+	let stream = futures::stream::unfold(state, move |(mut receiver, mut keep_alive)| async move {
+		let event = loop {
+			let alert = tokio::select! {
+				alert = receiver.recv() => match alert {
+					Ok(alert) => alert,
+					Err(broadcast::error::RecvError::Lagged(_)) => continue,
+					Err(broadcast::error::RecvError::Closed) => return None,
+				},
+				_ = keep_alive.tick() => break web::Bytes::from_static(b": keep-alive\n\n"),
+			};
+
+			if alert.user_id == user_id {
+				let data = serde_json::to_string(&alert).expect("alert to always be serializable");
+
+				break web::Bytes::from(format!("event: alert\ndata: {data}\n\n"));
+			}
+		};
+
+		Some((Ok::<_, actix_web::Error>(event), (receiver, keep_alive)))
+	});
+
+	Ok(HttpResponse::Ok()
+		.content_type("text/event-stream")
+		.insert_header((header::CACHE_CONTROL, "no-cache"))
+		.streaming(stream))
+}
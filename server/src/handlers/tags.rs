@@ -0,0 +1,152 @@
+use std::{
+	sync::{Arc, RwLock},
+	time::Duration,
+};
+
+use actix_web::{get, web, HttpResponse};
+use database::{
+	filter::{PseudoTag, PSEUDO_TAGS},
+	PostgresPool,
+};
+use diesel::{
+	sql_types::{BigInt, Int2, Text},
+	PgConnection, QueryResult, QueryableByName, RunQueryDsl,
+};
+use serde::Serialize;
+
+use crate::{auth::User, error::Error, handlers::stats::StatusCount, pool};
+
+/// How often the tag counts are recomputed in the background
+const REFRESH_INTERVAL: Duration = Duration::from_mins(5);
+
+#[derive(QueryableByName)]
+struct TagStatusCount {
+	#[diesel(sql_type = Text)]
+	tag: String,
+	#[diesel(sql_type = Int2)]
+	status: i16,
+	#[diesel(sql_type = BigInt)]
+	count: i64,
+}
+
+#[derive(Serialize)]
+pub struct FormattedTag {
+	pub tag: String,
+	pub count: i64,
+	/// The number of names with the tag in each status
+	pub statuses: Vec<StatusCount>,
+}
+
+pub struct Tags {
+	pub tags: Vec<FormattedTag>,
+	pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewTagsResponse<'a> {
+	/// Every tag stored on at least one name, most common first
+	pub data: &'a [FormattedTag],
+	pub pseudo_tags: &'static [PseudoTag],
+	pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The most recently computed tag counts, shared between workers.
+#[derive(Default)]
+pub struct TagCache(RwLock<Option<Arc<Tags>>>);
+
+impl TagCache {
+	/// Returns the cached tags, or `None` if they have not been computed yet.
+	fn cached(&self) -> Option<Arc<Tags>> {
+		self.0.read().expect("tags lock to not be poisoned").clone()
+	}
+
+	fn refresh(&self, connection: &mut PgConnection) -> QueryResult<Arc<Tags>> {
+		let tags = Arc::new(compute(connection)?);
+
+		*self.0.write().expect("tags lock to not be poisoned") = Some(tags.clone());
+
+		Ok(tags)
+	}
+}
+
+fn compute(connection: &mut PgConnection) -> QueryResult<Tags> {
+	let counts = diesel::sql_query(
+		"SELECT tag, status, COUNT(*) AS count FROM name, unnest(tags) AS tag \
+		 GROUP BY tag, status ORDER BY tag, status",
+	)
+	.load::<TagStatusCount>(connection)?;
+
+	let mut tags = Vec::<FormattedTag>::new();
+
+	// the rows are ordered by tag, so each tag's statuses are next to each other
+	for row in counts {
+		let status = StatusCount {
+			status: row.status.into(),
+			count: row.count,
+		};
+
+		match tags.last_mut() {
+			Some(tag) if tag.tag == row.tag => {
+				tag.count += row.count;
+				tag.statuses.push(status);
+			}
+			_ => tags.push(FormattedTag {
+				tag: row.tag,
+				count: row.count,
+				statuses: vec![status],
+			}),
+		}
+	}
+
+	tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+
+	Ok(Tags {
+		tags,
+		generated_at: chrono::Utc::now(),
+	})
+}
+
+/// Recomputes the tag counts every [`REFRESH_INTERVAL`], forever.
+pub fn refresh(pool: PostgresPool, cache: web::Data<TagCache>) {
+	loop {
+		let result = pool
+			.get()
+			.map_err(|e| e.to_string())
+			.and_then(|mut connection| {
+				// the counts cover every name, so they can take longer than a request is allowed to
+				database::without_statement_timeout(&mut connection, |connection| {
+					cache.refresh(connection)
+				})
+				.map_err(|e| e.to_string())
+			});
+
+		if let Err(e) = result {
+			eprintln!("could not refresh tags: {e}");
+		}
+
+		std::thread::sleep(REFRESH_INTERVAL);
+	}
+}
+
+#[get("/tags")]
+pub async fn view_tags(
+	_user: User,
+	pool: web::Data<PostgresPool>,
+	cache: web::Data<TagCache>,
+) -> Result<HttpResponse, Error> {
+	let tags = match cache.cached() {
+		Some(tags) => tags,
+		None => {
+			let cache = cache.into_inner();
+
+			pool::run(&pool, move |connection| Ok(cache.refresh(connection)?)).await?
+		}
+	};
+
+	Ok(HttpResponse::Ok().json(ViewTagsResponse {
+		data: &tags.tags,
+		pseudo_tags: PSEUDO_TAGS,
+		generated_at: tags.generated_at,
+	}))
+}
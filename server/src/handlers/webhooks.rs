@@ -0,0 +1,363 @@
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use database::{
+	crypto::Secret,
+	keys,
+	models::{NewWebhook, Webhook, WebhookChanges, WebhookDelivery},
+	schema,
+	webhooks::{self, DeliveryStatus, Event},
+	PostgresPool,
+};
+use diesel::{Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	auth::User,
+	error::{invalid, Error},
+	pool,
+};
+
+/// The most deliveries returned by a single request
+const MAX_LIMIT: i64 = 500;
+/// The longest URL accepted for a webhook
+const MAX_URL_LENGTH: usize = 2048;
+
+#[derive(Deserialize)]
+pub struct CreateWebhookOptions {
+	pub url: String,
+	pub events: Vec<Event>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateWebhookOptions {
+	pub url: Option<String>,
+	pub events: Option<Vec<Event>>,
+	pub active: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ViewDeliveriesOptions {
+	pub limit: Option<i64>,
+	/// Only return deliveries with an id less than this, to fetch the next page
+	pub before: Option<i64>,
+	pub status: Option<DeliveryStatus>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedWebhook {
+	pub id: i32,
+	pub url: String,
+	pub events: Vec<String>,
+	pub active: bool,
+	pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedDelivery {
+	pub id: i64,
+	pub event: String,
+	pub payload: serde_json::Value,
+	pub status: DeliveryStatus,
+	pub attempts: i16,
+	/// The status code of the last response, if the receiver responded
+	pub response_status: Option<i32>,
+	/// Why the last attempt failed
+	pub error: Option<String>,
+	/// When the next attempt will be made, if the delivery is still pending
+	pub next_attempt_at: Option<chrono::DateTime<chrono::Utc>>,
+	pub created_at: chrono::DateTime<chrono::Utc>,
+	pub delivered_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct ViewWebhooksResponse {
+	pub data: Vec<FormattedWebhook>,
+}
+
+#[derive(Serialize)]
+pub struct CreateWebhookResponse {
+	#[serde(flatten)]
+	pub data: FormattedWebhook,
+	/// The key used to sign payloads, which is only ever returned once
+	pub secret: String,
+}
+
+#[derive(Serialize)]
+pub struct ViewDeliveriesResponse {
+	pub data: Vec<FormattedDelivery>,
+}
+
+#[derive(Serialize)]
+pub struct WebhookResponse {
+	pub updated: bool,
+}
+
+impl From<Webhook> for FormattedWebhook {
+	fn from(webhook: Webhook) -> Self {
+		Self {
+			id: webhook.id,
+			url: webhook.url,
+			events: webhook.events,
+			active: webhook.active,
+			created_at: webhook.created_at,
+		}
+	}
+}
+
+impl From<WebhookDelivery> for FormattedDelivery {
+	fn from(delivery: WebhookDelivery) -> Self {
+		let status = DeliveryStatus::from(delivery.status);
+
+		Self {
+			id: delivery.id,
+			event: delivery.event,
+			payload: delivery.payload,
+			status,
+			attempts: delivery.attempts,
+			response_status: delivery.response_status,
+			error: delivery.error,
+			next_attempt_at: (status == DeliveryStatus::Pending)
+				.then_some(delivery.next_attempt_at),
+			created_at: delivery.created_at,
+			delivered_at: delivery.delivered_at,
+		}
+	}
+}
+
+fn validate_url(url: &str) -> Result<(), Error> {
+	if url.len() > MAX_URL_LENGTH {
+		return Err(invalid("url", "must be at most 2048 characters"));
+	}
+
+	let url = match url::Url::parse(url) {
+		Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+		_ => return Err(invalid("url", "must be an absolute http or https URL")),
+	};
+
+	// domains are resolved again before every delivery, since they can change to point anywhere
+	let is_public = match url.host() {
+		Some(url::Host::Domain(domain)) => {
+			let domain = domain.trim_end_matches('.');
+
+			domain != "localhost" && !domain.ends_with(".localhost")
+		}
+		Some(url::Host::Ipv4(ip)) => webhooks::is_public(ip.into()),
+		Some(url::Host::Ipv6(ip)) => webhooks::is_public(ip.into()),
+		None => return Err(invalid("url", "must be an absolute http or https URL")),
+	};
+
+	if is_public {
+		Ok(())
+	} else {
+		Err(invalid("url", "must not point to a private address"))
+	}
+}
+
+/// Returns the events as they are stored, without duplicates.
+fn prepare_events(events: &[Event]) -> Result<Vec<&'static str>, Error> {
+	if events.is_empty() {
+		return Err(invalid("events", "must contain at least one event"));
+	}
+
+	let mut events = events
+		.iter()
+		.map(|event| event.as_str())
+		.collect::<Vec<_>>();
+
+	events.sort_unstable();
+	events.dedup();
+
+	Ok(events)
+}
+
+#[get("/webhooks")]
+pub async fn view_webhooks(
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let webhooks = pool::run(&pool, move |connection| {
+		Ok(schema::webhook::table
+			.filter(schema::webhook::user_id.eq(user.id))
+			.order(schema::webhook::created_at.desc())
+			.load::<Webhook>(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(ViewWebhooksResponse {
+		data: webhooks.into_iter().map(FormattedWebhook::from).collect(),
+	}))
+}
+
+#[post("/webhooks")]
+pub async fn create_webhook(
+	data: web::Json<CreateWebhookOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+	secret: web::Data<Secret>,
+) -> Result<HttpResponse, Error> {
+	validate_url(&data.url)?;
+	let events = prepare_events(&data.events)?;
+
+	let signing_secret = keys::generate();
+	let encrypted_secret = secret.encrypt(&signing_secret);
+	let url = data.into_inner().url;
+
+	let webhook = pool::run(&pool, move |connection| {
+		Ok(diesel::insert_into(schema::webhook::table)
+			.values(NewWebhook {
+				user_id: user.id,
+				url: &url,
+				secret: encrypted_secret,
+				events,
+			})
+			.get_result::<Webhook>(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Created().json(CreateWebhookResponse {
+		data: webhook.into(),
+		secret: signing_secret,
+	}))
+}
+
+#[get("/webhooks/{id}")]
+pub async fn view_webhook(
+	id: web::Path<i32>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let id = id.into_inner();
+
+	let webhook = pool::run(&pool, move |connection| {
+		schema::webhook::table
+			.filter(schema::webhook::id.eq(id))
+			.filter(schema::webhook::user_id.eq(user.id))
+			.get_result::<Webhook>(connection)
+			.optional()?
+			.ok_or(Error::NotFound)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(FormattedWebhook::from(webhook)))
+}
+
+#[patch("/webhooks/{id}")]
+pub async fn update_webhook(
+	id: web::Path<i32>,
+	data: web::Json<UpdateWebhookOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	if let Some(url) = &data.url {
+		validate_url(url)?;
+	}
+
+	let events = data.events.as_deref().map(prepare_events).transpose()?;
+
+	if data.url.is_none() && events.is_none() && data.active.is_none() {
+		return Err(Error::InvalidBody {
+			field: None,
+			message: "at least one of url, events or active must be provided".to_string(),
+		});
+	}
+
+	let id = id.into_inner();
+	let data = data.into_inner();
+
+	let webhook = pool::run(&pool, move |connection| {
+		connection.transaction(|connection| {
+			let webhook = diesel::update(schema::webhook::table)
+				.filter(schema::webhook::id.eq(id))
+				.filter(schema::webhook::user_id.eq(user.id))
+				.set(WebhookChanges {
+					url: data.url.as_deref(),
+					events,
+					active: data.active,
+				})
+				.get_result::<Webhook>(connection)
+				.optional()?
+				.ok_or(Error::NotFound)?;
+
+			if !webhook.active {
+				webhooks::cancel_pending(connection, webhook.id)?;
+			}
+
+			Ok(webhook)
+		})
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(FormattedWebhook::from(webhook)))
+}
+
+#[delete("/webhooks/{id}")]
+pub async fn delete_webhook(
+	id: web::Path<i32>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let id = id.into_inner();
+
+	let updates = pool::run(&pool, move |connection| {
+		Ok(diesel::delete(schema::webhook::table)
+			.filter(schema::webhook::id.eq(id))
+			.filter(schema::webhook::user_id.eq(user.id))
+			.execute(connection)?)
+	})
+	.await?;
+
+	if updates == 0 {
+		return Err(Error::NotFound);
+	}
+
+	Ok(HttpResponse::Ok().json(WebhookResponse { updated: true }))
+}
+
+#[get("/webhooks/{id}/deliveries")]
+pub async fn view_deliveries(
+	id: web::Path<i32>,
+	options: web::Query<ViewDeliveriesOptions>,
+	user: User,
+	pool: web::Data<PostgresPool>,
+) -> Result<HttpResponse, Error> {
+	let id = id.into_inner();
+	let options = options.into_inner();
+
+	let deliveries = pool::run(&pool, move |connection| {
+		let exists = diesel::select(diesel::dsl::exists(
+			schema::webhook::table
+				.filter(schema::webhook::id.eq(id))
+				.filter(schema::webhook::user_id.eq(user.id)),
+		))
+		.get_result::<bool>(connection)?;
+
+		if !exists {
+			return Err(Error::NotFound);
+		}
+
+		let mut deliveries = schema::webhook_delivery::table
+			.filter(schema::webhook_delivery::webhook_id.eq(id))
+			.order(schema::webhook_delivery::id.desc())
+			.limit(options.limit.unwrap_or(50).clamp(0, MAX_LIMIT))
+			.into_boxed();
+
+		if let Some(before) = options.before {
+			deliveries = deliveries.filter(schema::webhook_delivery::id.lt(before));
+		}
+
+		if let Some(status) = options.status {
+			deliveries = deliveries.filter(schema::webhook_delivery::status.eq(i16::from(status)));
+		}
+
+		Ok(deliveries.load::<WebhookDelivery>(connection)?)
+	})
+	.await?;
+
+	Ok(HttpResponse::Ok().json(ViewDeliveriesResponse {
+		data: deliveries
+			.into_iter()
+			.map(FormattedDelivery::from)
+			.collect(),
+	}))
+}
@@ -1,15 +1,126 @@
+mod auth;
+mod cursor;
+mod error;
 mod handlers;
+mod pool;
+
+use std::time::{Duration, Instant};
 
 use actix_cors::Cors;
-use actix_web::{http::header, web, App, HttpServer};
+use actix_web::{
+	dev::Service,
+	error::{JsonPayloadError, PathError, QueryPayloadError},
+	http::header,
+	web, App, HttpRequest, HttpResponse, HttpServer,
+};
+use error::Error;
+
+/// How long in-flight requests are given to finish when shutting down, in seconds
+const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30;
+/// The most database connections the server opens, which also bounds the number of
+/// threads running queries
+const DEFAULT_POOL_SIZE: u32 = 10;
+/// How long a single query can run before it is cancelled, in seconds
+const DEFAULT_STATEMENT_TIMEOUT: u64 = 10;
+/// How long a request waits for a free database connection before giving up
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Rejects bodies that are valid JSON but have missing or invalid fields with a 422,
+/// and any other malformed body with a 400.
+fn json_error(error: JsonPayloadError, _: &HttpRequest) -> actix_web::Error {
+	match &error {
+		JsonPayloadError::Deserialize(e) if e.is_data() => Error::InvalidBody {
+			field: None,
+			message: e.to_string(),
+		},
+		_ => Error::BadRequest(error.to_string()),
+	}
+	.into()
+}
+
+fn query_error(error: QueryPayloadError, _: &HttpRequest) -> actix_web::Error {
+	Error::BadRequest(error.to_string()).into()
+}
+
+/// Rejects paths with segments that cannot be parsed, such as a non-numeric id, with a 404.
+fn path_error(_: PathError, _: &HttpRequest) -> actix_web::Error {
+	Error::NotFound.into()
+}
+
+/// Resolves when the process is asked to stop, with Ctrl+C or `SIGTERM`.
+async fn shutdown_signal() {
+	#[cfg(unix)]
+	let terminate = async {
+		tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+			.expect("SIGTERM handler to be installed")
+			.recv()
+			.await;
+	};
+	#[cfg(not(unix))]
+	let terminate = std::future::pending::<()>();
+
+	tokio::select! {
+		_ = tokio::signal::ctrl_c() => {}
+		() = terminate => {}
+	}
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
 	dotenvy::dotenv().ok();
 
-	let pool = database::get_pool();
+	metrics::server::register();
+
+	let pool_size = std::env::var("DATABASE_POOL_SIZE").map_or(DEFAULT_POOL_SIZE, |size| {
+		size.parse()
+			.expect("DATABASE_POOL_SIZE must be a positive number")
+	});
+	let statement_timeout =
+		std::env::var("STATEMENT_TIMEOUT").map_or(DEFAULT_STATEMENT_TIMEOUT, |timeout| {
+			timeout
+				.parse()
+				.expect("STATEMENT_TIMEOUT must be a number of seconds")
+		});
+	let pool = database::get_bounded_pool(
+		pool_size,
+		Duration::from_secs(statement_timeout),
+		CONNECTION_TIMEOUT,
+	);
+	pool::limit(pool_size as usize, CONNECTION_TIMEOUT);
+	let secret = database::crypto::Secret::from_env();
+	let stats = web::Data::new(handlers::stats::StatsCache::default());
+	let tags = web::Data::new(handlers::tags::TagCache::default());
+	let names = web::Data::new(handlers::names::NameCache::default());
+	let shutting_down = web::Data::new(handlers::monitoring::ShuttingDown::default());
+	let shutdown_timeout =
+		std::env::var("SHUTDOWN_TIMEOUT").map_or(DEFAULT_SHUTDOWN_TIMEOUT, |timeout| {
+			timeout
+				.parse()
+				.expect("SHUTDOWN_TIMEOUT must be a number of seconds")
+		});
+	let (sender, _) = tokio::sync::broadcast::channel::<handlers::names::FormattedName>(256);
+	let (alert_sender, _) =
+		tokio::sync::broadcast::channel::<handlers::alerts::FormattedAlert>(256);
 
-	HttpServer::new(move || {
+	std::thread::spawn({
+		let pool = pool.clone();
+		let sender = sender.clone();
+		let alert_sender = alert_sender.clone();
+		let names = names.clone();
+
+		move || handlers::stream::listen(pool, sender, alert_sender, names)
+	});
+
+	std::thread::spawn({
+		let pool = pool.clone();
+		let tags = tags.clone();
+
+		move || handlers::tags::refresh(pool, tags)
+	});
+
+	// the factory takes its own copy, so keep one to flag when shutting down
+	let shutdown = shutting_down.clone();
+	let server = HttpServer::new(move || {
 		let cors = Cors::default()
 			.allow_any_origin()
 			.allow_any_method()
@@ -18,13 +129,101 @@ async fn main() -> std::io::Result<()> {
 
 		App::new()
 			.wrap(cors)
+			.wrap_fn(|request, service| {
+				let start = Instant::now();
+				let method = request.method().to_string();
+				// unmatched paths share a label so that arbitrary paths do not create a series each
+				let route = request
+					.match_pattern()
+					.unwrap_or_else(|| "unmatched".to_string());
+				let response = service.call(request);
+
+				async move {
+					let response = response.await?;
+
+					metrics::server::REQUEST_DURATION
+						.with_label_values(&[&method, &route, response.status().as_str()])
+						.observe(start.elapsed().as_secs_f64());
+
+					Ok(response)
+				}
+			})
+			.app_data(web::JsonConfig::default().error_handler(json_error))
+			.app_data(web::QueryConfig::default().error_handler(query_error))
+			.app_data(web::PathConfig::default().error_handler(path_error))
 			.app_data(web::Data::new(pool.clone()))
+			.app_data(web::Data::new(sender.clone()))
+			.app_data(web::Data::new(alert_sender.clone()))
+			.app_data(web::Data::new(secret.clone()))
+			.app_data(stats.clone())
+			.app_data(tags.clone())
+			.app_data(names.clone())
+			.app_data(shutting_down.clone())
+			.service(handlers::accounts::view_accounts)
+			.service(handlers::accounts::create_account)
+			.service(handlers::accounts::update_account)
+			.service(handlers::accounts::delete_account)
+			.service(handlers::keys::view_keys)
+			.service(handlers::keys::create_key)
+			.service(handlers::keys::rotate_key)
+			.service(handlers::keys::revoke_key)
+			.service(handlers::alerts::view_alerts)
+			.service(handlers::export::export_names)
+			.service(handlers::likes::view_likes)
+			.service(handlers::likes::like_names_bulk)
+			.service(handlers::likes::unlike_names_bulk)
+			.service(handlers::likes::like_name)
+			.service(handlers::likes::unlike_name)
+			.service(handlers::monitoring::healthz)
+			.service(handlers::monitoring::readyz)
+			.service(handlers::monitoring::view_metrics)
 			.service(handlers::names::view_names)
-			.service(handlers::names::like_name)
-			.service(handlers::names::dislike_name)
+			.service(handlers::names::view_name)
+			.service(handlers::names::view_name_history)
+			.service(handlers::proxies::view_proxies)
+			.service(handlers::proxies::import_proxies)
+			.service(handlers::proxies::create_proxy)
+			.service(handlers::proxies::update_proxy)
+			.service(handlers::proxies::delete_proxy)
+			.service(handlers::searches::view_searches)
+			.service(handlers::searches::create_search)
+			.service(handlers::searches::view_search)
+			.service(handlers::searches::update_search)
+			.service(handlers::searches::delete_search)
 			.service(handlers::snipe::create_snipe)
+			.service(handlers::snipe::view_snipes)
+			.service(handlers::snipe::view_snipe)
+			.service(handlers::snipe::update_snipe)
+			.service(handlers::snipe::delete_snipe)
+			.service(handlers::stats::view_stats)
+			.service(handlers::stream::stream_alerts)
+			.service(handlers::stream::stream_names)
+			.service(handlers::tags::view_tags)
+			.service(handlers::webhooks::view_webhooks)
+			.service(handlers::webhooks::create_webhook)
+			.service(handlers::webhooks::view_webhook)
+			.service(handlers::webhooks::update_webhook)
+			.service(handlers::webhooks::delete_webhook)
+			.service(handlers::webhooks::view_deliveries)
+			.default_service(web::to(|| async {
+				Err::<HttpResponse, _>(Error::NotFound)
+			}))
 	})
 	.bind(("0.0.0.0", 8080))?
-	.run()
-	.await
+	.shutdown_timeout(shutdown_timeout)
+	.disable_signals()
+	.run();
+
+	let handle = server.handle();
+
+	actix_web::rt::spawn(async move {
+		shutdown_signal().await;
+
+		println!("Shutting down, waiting up to {shutdown_timeout} seconds for requests to finish");
+
+		shutdown.begin();
+		handle.stop(true).await;
+	});
+
+	server.await
 }
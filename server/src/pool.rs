@@ -0,0 +1,58 @@
+use std::{sync::OnceLock, time::Duration};
+
+use actix_web::web;
+use database::PostgresPool;
+use diesel::PgConnection;
+use tokio::sync::Semaphore;
+
+use crate::error::Error;
+
+/// Bounds how many queries run at once across every worker
+struct Limit {
+	permits: Semaphore,
+	/// How long a query waits for a permit before giving up
+	timeout: Duration,
+}
+
+static LIMIT: OnceLock<Limit> = OnceLock::new();
+
+/// Limits the number of queries running at once to `connections`.
+///
+/// Each worker has its own blocking thread pool, so bounding the threads would
+/// still allow `workers × threads` queries to wait on the connection pool.
+pub fn limit(connections: usize, timeout: Duration) {
+	LIMIT
+		.set(Limit {
+			permits: Semaphore::new(connections),
+			timeout,
+		})
+		.ok()
+		.expect("the query limit can only be set once");
+}
+
+/// Runs `query` with a connection from the pool on the blocking thread pool, so
+/// that slow queries never stall the worker handling other requests.
+///
+/// At most as many queries as there are connections run at once, so once every
+/// connection is busy the query waits for one to become free without taking up
+/// a blocking thread.
+pub async fn run<T, F>(pool: &PostgresPool, query: F) -> Result<T, Error>
+where
+	T: Send + 'static,
+	F: FnOnce(&mut PgConnection) -> Result<T, Error> + Send + 'static,
+{
+	let limit = LIMIT.get().expect("the query limit to be set");
+	let _permit = tokio::time::timeout(limit.timeout, limit.permits.acquire())
+		.await
+		.map_err(|_| Error::Unavailable)?
+		.map_err(|_| Error::Unavailable)?;
+
+	let pool = pool.clone();
+
+	web::block(move || {
+		let connection = &mut pool.get()?;
+
+		query(connection)
+	})
+	.await?
+}